@@ -0,0 +1,35 @@
+//! Compares generating a large table with `NoFormatting`, which is skipped by `MarkupSth`, to a
+//! formatter, which applies no changes as well, but has to be asked for every sequence.
+
+use bencher::{benchmark_group, benchmark_main, Bencher};
+use markupsth::{format::FormatChanges, FnFormatter, Formatter, Language, MarkupSth, NoFormatting};
+
+fn write_table(formatter: Box<dyn Formatter>) -> String {
+    let mut document = String::new();
+    let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+    mus.set_formatter(formatter);
+    mus.open("table").unwrap();
+    for row in 0..1000 {
+        mus.open("tr").unwrap();
+        for col in 0..10 {
+            mus.open("td").unwrap();
+            mus.text_unchecked(&(row * col).to_string()).unwrap();
+            mus.close().unwrap();
+        }
+        mus.close().unwrap();
+    }
+    mus.close().unwrap();
+    mus.finalize().unwrap();
+    document
+}
+
+fn no_formatting(b: &mut Bencher) {
+    b.iter(|| write_table(Box::new(NoFormatting::new())));
+}
+
+fn checked_no_changes(b: &mut Bencher) {
+    b.iter(|| write_table(Box::new(FnFormatter::from_fn(|_| FormatChanges::nothing()))));
+}
+
+benchmark_group!(benches, no_formatting, checked_no_changes);
+benchmark_main!(benches);
@@ -0,0 +1,97 @@
+//! This module contains `AsyncMarkupSth`, an adapter for writing documents into any implementor of
+//! `tokio::io::AsyncWrite`, e.g. a socket. `MarkupSth` itself writes synchronously into an owned
+//! buffer, which gets written to the writer on demand by `flush()` and at last by `finalize()`.
+//! Requires feature `tokio`.
+//!
+//! Note, that `MarkupSth` completes tags lazily, so the last tag may still be incomplete after a
+//! `flush()`. The rest follows with the next `flush()` or `finalize()`.
+
+use crate::{Language, MarkupSth, Result};
+use std::ops::{Deref, DerefMut};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// A buffered `MarkupSth`, which writes into an asynchronous writer. All methods of `MarkupSth`
+/// are available by dereferencing.
+#[derive(Debug)]
+pub struct AsyncMarkupSth<W> {
+    /// The underlying `MarkupSth`, which writes into an owned buffer.
+    markup: MarkupSth<'static>,
+    /// The asynchronous writer, the buffer gets written to.
+    writer: W,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncMarkupSth<W> {
+    /// Creates a new AsyncMarkupSth instance, which writes into `writer`.
+    pub fn new(writer: W, ml: Language) -> Result<AsyncMarkupSth<W>> {
+        Ok(AsyncMarkupSth {
+            markup: MarkupSth::buffered(ml)?,
+            writer,
+        })
+    }
+
+    /// Writes everything buffered so far into the writer and flushes the writer.
+    pub async fn flush(&mut self) -> Result<()> {
+        let buffer = self.markup.take_buffer();
+        if !buffer.is_empty() {
+            self.writer.write_all(buffer.as_bytes()).await?;
+        }
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    /// Finalizes the document like `MarkupSth::finalize()`, writes the rest into the writer and
+    /// returns the writer, e.g. for shutting it down.
+    pub async fn finalize(mut self) -> Result<W> {
+        self.markup.finalize_document()?;
+        self.flush().await?;
+        Ok(self.writer)
+    }
+}
+
+impl<W> Deref for AsyncMarkupSth<W> {
+    type Target = MarkupSth<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.markup
+    }
+}
+
+impl<W> DerefMut for AsyncMarkupSth<W> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.markup
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Formatter, NoFormatting};
+    use tokio::io::AsyncReadExt;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn write_into_duplex_stream() {
+        block_on(async {
+            let (writer, mut reader) = tokio::io::duplex(1024);
+            let mut mus = AsyncMarkupSth::new(writer, Language::Html).unwrap();
+            mus.set_formatter(Box::new(NoFormatting::new()));
+            mus.open("body").unwrap();
+            mus.open("p").unwrap();
+            mus.text("Hello").unwrap();
+            mus.flush().await.unwrap();
+            mus.close_all().unwrap();
+            let writer = mus.finalize().await.unwrap();
+            drop(writer);
+
+            let mut result = String::new();
+            reader.read_to_string(&mut result).await.unwrap();
+            assert_eq!(result, "<!DOCTYPE html>\n<body><p>Hello</p></body>");
+        });
+    }
+}
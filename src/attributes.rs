@@ -0,0 +1,120 @@
+//! This module contains `Attributes`, a small map of properties, which preserves the order of
+//! insertion. In contrast to a `HashMap`, properties will always be written in the same order by
+//! `MarkupSth::properties_map()`.
+//!
+//! ### Example
+//!
+//! ```
+//! use markupsth::{Attributes, Language, MarkupSth, NoFormatting, Formatter};
+//!
+//! let mut attrs = Attributes::new();
+//! attrs.insert("id", "main");
+//! attrs.insert("class", "box");
+//!
+//! let mut document = String::new();
+//! let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+//! mus.set_formatter(Box::new(NoFormatting::new()));
+//! mus.open("div").unwrap();
+//! mus.properties_map(&attrs).unwrap();
+//! mus.close().unwrap();
+//! mus.finalize().unwrap();
+//! assert_eq!(document, concat!["<!DOCTYPE html>\n", r#"<div id="main" class="box"></div>"#]);
+//! ```
+
+/// An insertion-order-preserving map of properties (name, value).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Attributes(Vec<(String, String)>);
+
+impl Attributes {
+    /// Creates a new, empty map of properties.
+    pub fn new() -> Attributes {
+        Attributes(Vec::new())
+    }
+
+    /// Inserts a property. If a property with that name already exists, its value will be replaced
+    /// in place, so it keeps its position, and the old value will be returned.
+    pub fn insert(&mut self, name: &str, value: &str) -> Option<String> {
+        match self.0.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => Some(std::mem::replace(v, value.to_string())),
+            None => {
+                self.0.push((name.to_string(), value.to_string()));
+                None
+            }
+        }
+    }
+
+    /// Returns the value of the property `name`, if existing.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Removes the property `name` and returns its value, if existing. The order of the remaining
+    /// properties will be kept.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let index = self.0.iter().position(|(n, _)| n == name)?;
+        Some(self.0.remove(index).1)
+    }
+
+    /// Returns the number of properties.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true`, if there are no properties.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns an iterator over all properties (name, value) in order of insertion.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+}
+
+impl IntoIterator for Attributes {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Attributes {
+    type Item = &'a (String, String);
+    type IntoIter = std::slice::Iter<'a, (String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn order_of_insertion() {
+        let mut attrs = Attributes::new();
+        assert!(attrs.is_empty());
+        attrs.insert("src", "image.jpg");
+        attrs.insert("alt", "Image");
+        attrs.insert("width", "100");
+        assert_eq!(attrs.insert("alt", "Picture"), Some("Image".to_string()));
+        assert_eq!(attrs.len(), 3);
+        assert_eq!(attrs.get("alt"), Some("Picture"));
+        assert_eq!(attrs.get("height"), None);
+        assert_eq!(
+            attrs.iter().collect::<Vec<_>>(),
+            vec![("src", "image.jpg"), ("alt", "Picture"), ("width", "100")]
+        );
+
+        assert_eq!(attrs.remove("src"), Some("image.jpg".to_string()));
+        assert_eq!(attrs.remove("src"), None);
+        let names: Vec<String> = attrs.into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, vec!["alt".to_string(), "width".to_string()]);
+    }
+}
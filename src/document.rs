@@ -0,0 +1,352 @@
+//! This module contains the `Document`, the target `MarkupSth` writes into. A `Document` is
+//! either a `String`, a file or any other implementor of `std::fmt::Write`, and keeps track of the
+//! current line, which is needed for column-based formatting.
+
+use crate::format::display_width;
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::Path,
+};
+
+/// The target of a `Document`.
+enum Target<'d> {
+    /// A `String`, which can also be read back.
+    String(&'d mut String),
+    /// Any other implementor of `std::fmt::Write`.
+    Writer(&'d mut dyn fmt::Write),
+    /// A file, which has been created for writing.
+    File(io::BufWriter<fs::File>),
+    /// An owned buffer, which gets drained by its owner, e.g. to write it asynchronously.
+    #[cfg(feature = "tokio")]
+    Buffer(String),
+}
+
+/// Hook, which transforms each completed line.
+pub(crate) type LineHook<'d> = Box<dyn FnMut(&str) -> String + 'd>;
+
+/// The document `MarkupSth` writes into.
+pub(crate) struct Document<'d> {
+    /// Where everything gets written to.
+    target: Target<'d>,
+    /// Optional hook for completed lines, which are buffered in `line` until completion.
+    line_hook: Option<LineHook<'d>>,
+    /// The current, not yet completed line, if `line_hook` is set.
+    line: String,
+    /// Display width of the current (last) line.
+    column: usize,
+    /// Whether the current (last) line contains only whitespaces.
+    line_blank: bool,
+    /// Number of bytes written into the target, also into targets which cannot be read back.
+    written: usize,
+    /// Length of the content of a `String`, which has been there before, see `clear()`.
+    start: usize,
+    /// The last I/O error of a file, which can only be reported as `fmt::Error` while writing.
+    io_error: Option<io::Error>,
+    /// Rolling FNV-1a hash of all bytes in the target.
+    #[cfg(feature = "digest")]
+    digest: u64,
+}
+
+/// Offset basis of the 64-bit FNV-1a hash.
+#[cfg(feature = "digest")]
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Prime of the 64-bit FNV-1a hash.
+#[cfg(feature = "digest")]
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Updates the 64-bit FNV-1a hash `hash` by `bytes`.
+#[cfg(feature = "digest")]
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |h, b| (h ^ *b as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Returns the display width of the last line of `content` and whether it contains only
+/// whitespaces.
+fn last_line_state(content: &str) -> (usize, bool) {
+    let line = content.rsplit('\n').next().unwrap_or("");
+    (display_width(line), line.trim().is_empty())
+}
+
+impl<'d> Document<'d> {
+    /// Creates a `Document`, which writes into a `String`.
+    pub(crate) fn from_string(document: &'d mut String) -> Document<'d> {
+        let (column, line_blank) = last_line_state(document);
+        Document {
+            #[cfg(feature = "digest")]
+            digest: fnv1a(FNV_OFFSET, document.as_bytes()),
+            start: document.len(),
+            io_error: None,
+            target: Target::String(document),
+            line_hook: None,
+            line: String::new(),
+            column,
+            line_blank,
+            written: 0,
+        }
+    }
+
+    /// Creates a `Document`, which writes into any implementor of `std::fmt::Write`.
+    pub(crate) fn from_writer(writer: &'d mut dyn fmt::Write) -> Document<'d> {
+        Document {
+            target: Target::Writer(writer),
+            line_hook: None,
+            line: String::new(),
+            column: 0,
+            line_blank: true,
+            written: 0,
+            start: 0,
+            io_error: None,
+            #[cfg(feature = "digest")]
+            digest: FNV_OFFSET,
+        }
+    }
+
+    /// Creates a `Document`, which writes into a new file at `path`. An existing file will be
+    /// truncated.
+    pub(crate) fn new_file(path: impl AsRef<Path>) -> io::Result<Document<'d>> {
+        let file = fs::File::create(path)?;
+        Ok(Document {
+            target: Target::File(io::BufWriter::new(file)),
+            line_hook: None,
+            line: String::new(),
+            column: 0,
+            line_blank: true,
+            written: 0,
+            start: 0,
+            io_error: None,
+            #[cfg(feature = "digest")]
+            digest: FNV_OFFSET,
+        })
+    }
+
+    /// Creates a `Document`, which writes into an owned buffer, see `take_buffer()`.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn buffered() -> Document<'d> {
+        Document {
+            target: Target::Buffer(String::new()),
+            line_hook: None,
+            line: String::new(),
+            column: 0,
+            line_blank: true,
+            written: 0,
+            start: 0,
+            io_error: None,
+            #[cfg(feature = "digest")]
+            digest: FNV_OFFSET,
+        }
+    }
+
+    /// Takes the content of an owned buffer, which has been written since the last call. Returns
+    /// an empty `String` for any other target.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn take_buffer(&mut self) -> String {
+        match &mut self.target {
+            Target::Buffer(b) => std::mem::take(b),
+            _ => String::new(),
+        }
+    }
+
+    /// Flushes buffered content into the target, e.g. into a file. Returns the last I/O error,
+    /// which occurred while writing, if any.
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        if let Some(e) = self.io_error.take() {
+            return Err(e);
+        }
+        match &mut self.target {
+            Target::File(f) => f.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets a hook, which transforms each line, when it gets completed. Lines will be buffered
+    /// until completion from now on.
+    pub(crate) fn set_line_hook(&mut self, hook: LineHook<'d>) {
+        self.line_hook = Some(hook);
+    }
+
+    /// Completes the current (last) line, which may be buffered due to a line hook.
+    pub(crate) fn flush_line(&mut self) -> fmt::Result {
+        if let Some(hook) = self.line_hook.as_mut() {
+            let line = hook(&self.line);
+            self.line.clear();
+            self.write_target(&line)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the byte order mark (U+FEFF, in UTF-8 `EF BB BF`) into the target directly, without
+    /// affecting the current line.
+    pub(crate) fn write_bom(&mut self) -> fmt::Result {
+        self.write_target("\u{FEFF}")
+    }
+
+    /// Internal method to write into the target directly.
+    fn write_target(&mut self, s: &str) -> fmt::Result {
+        #[cfg(feature = "digest")]
+        {
+            self.digest = fnv1a(self.digest, s.as_bytes());
+        }
+        self.written += s.len();
+        match &mut self.target {
+            Target::String(d) => d.push_str(s),
+            Target::Writer(w) => w.write_str(s)?,
+            Target::File(f) => {
+                if let Err(e) = f.write_all(s.as_bytes()) {
+                    self.io_error = Some(e);
+                    return Err(fmt::Error);
+                }
+            }
+            #[cfg(feature = "tokio")]
+            Target::Buffer(b) => b.push_str(s),
+        }
+        Ok(())
+    }
+
+    /// Clears the content of the document, if it is written into a `String` or an owned buffer,
+    /// and returns whether it has been cleared. Other targets cannot be cleared. Content of a
+    /// `String`, which has been there before, is kept.
+    pub(crate) fn clear(&mut self) -> bool {
+        let start = self.start;
+        let prefix = match &mut self.target {
+            Target::String(s) => {
+                s.truncate(start);
+                s.as_str()
+            }
+            #[cfg(feature = "tokio")]
+            Target::Buffer(b) => {
+                b.clear();
+                ""
+            }
+            _ => return false,
+        };
+        (self.column, self.line_blank) = last_line_state(prefix);
+        #[cfg(feature = "digest")]
+        {
+            self.digest = fnv1a(FNV_OFFSET, prefix.as_bytes());
+        }
+        self.line.clear();
+        self.written = 0;
+        true
+    }
+
+    /// Takes the last I/O error, which occurred while writing, if any.
+    pub(crate) fn take_io_error(&mut self) -> Option<io::Error> {
+        self.io_error.take()
+    }
+
+    /// Returns the content of the document, if it is written into a `String`. A line, which is
+    /// buffered due to a line hook, is not included.
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match &self.target {
+            Target::String(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Returns the length of the document in bytes. For a `String` this is its length, including
+    /// content, which has been there before. For any other target, this is the number of bytes
+    /// written so far, also those already taken from an owned buffer. A line, which is buffered
+    /// due to a line hook, is not included.
+    pub(crate) fn len(&self) -> usize {
+        match &self.target {
+            Target::String(s) => s.len(),
+            _ => self.written,
+        }
+    }
+
+    /// Returns the FNV-1a hash of all content in the target, see `MarkupSth::digest()`.
+    #[cfg(feature = "digest")]
+    pub(crate) fn digest(&self) -> u64 {
+        self.digest
+    }
+
+    /// Returns the display width of the current (last) line, see `format::display_width`.
+    pub(crate) fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Returns whether the current (last) line contains only whitespaces, e.g. the indenting after
+    /// a linefeed.
+    pub(crate) fn is_line_blank(&self) -> bool {
+        self.line_blank
+    }
+}
+
+impl fmt::Write for Document<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.line_hook.is_some() {
+            let mut lines = s.split('\n');
+            self.line.push_str(lines.next().unwrap_or(""));
+            for line in lines {
+                self.flush_line()?;
+                self.write_target("\n")?;
+                self.line.push_str(line);
+            }
+        } else {
+            self.write_target(s)?;
+        }
+        match s.rfind('\n') {
+            Some(i) => {
+                self.column = display_width(&s[i + 1..]);
+                self.line_blank = s[i + 1..].trim().is_empty();
+            }
+            None => {
+                self.column += display_width(s);
+                self.line_blank = self.line_blank && s.trim().is_empty();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Document<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.target {
+            Target::String(s) => f.debug_tuple("Document::String").field(s).finish(),
+            Target::Writer(_) => f.write_str("Document::Writer"),
+            Target::File(_) => f.write_str("Document::File"),
+            #[cfg(feature = "tokio")]
+            Target::Buffer(b) => f.debug_tuple("Document::Buffer").field(b).finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Write;
+
+    #[test]
+    fn write_multibyte_chars_into_file() {
+        let dir = std::env::temp_dir().join(format!("markupsth-doc-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chars.txt");
+
+        let mut document = Document::new_file(&path).unwrap();
+        for c in ['a', 'é', '—', '漢', '🦀'] {
+            document.write_char(c).unwrap();
+        }
+        document.flush().unwrap();
+        assert_eq!(document.column(), display_width("aé—漢🦀"));
+        drop(document);
+
+        assert_eq!(fs::read(&path).unwrap(), "aé—漢🦀".as_bytes());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn keep_io_error_of_file() {
+        // Writing into /dev/full always fails with ENOSPC. Content larger than the buffer of the
+        // file is written immediately, so nothing is left to be flushed afterwards.
+        let mut document = Document::new_file("/dev/full").unwrap();
+        assert_eq!(document.write_str(&"x".repeat(64 * 1024)), Err(fmt::Error));
+        let err = document.flush().unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(28));
+        assert!(document.flush().is_ok());
+    }
+}
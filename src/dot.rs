@@ -0,0 +1,200 @@
+//! This module contains `DotSth`, a writer for graph descriptions in the DOT language of Graphviz.
+//! It reuses `MarkupSth` with a small custom syntax: Graphs are tag pairs delimited by braces and
+//! statements are self-closing tags terminated by `;`, so its formatter handles the indenting.
+//!
+//! ### Example
+//!
+//! ```
+//! use markupsth::DotSth;
+//!
+//! let mut document = String::new();
+//! let mut dot = DotSth::new(&mut document).unwrap();
+//! dot.begin_graph(true, "G").unwrap();
+//! dot.node("a", &[("label", "Start")]).unwrap();
+//! dot.edge("a", "b", &[]).unwrap();
+//! dot.end_graph().unwrap();
+//! dot.finalize().unwrap();
+//! assert_eq!(document, "digraph G {\n    a [label=\"Start\"];\n    a -> b;\n}");
+//! ```
+
+use crate::{
+    format::{FormatChanges, Sequence, DEFAULT_INDENT},
+    formatters::FnFormatter,
+    syntax::{Insertion::*, Language, SelfClosingTagConfig, SyntaxConfig, TagPairConfig},
+    MarkupSth, Result,
+};
+use std::{borrow::Cow, fmt::Write};
+
+/// A writer for graphs in the DOT language of Graphviz, based on `MarkupSth`.
+#[derive(Debug)]
+pub struct DotSth<'d> {
+    /// The underlying writer.
+    markup: MarkupSth<'d>,
+    /// Whether the open graph is directed, `None` if no graph is open.
+    directed: Option<bool>,
+}
+
+impl<'d> DotSth<'d> {
+    /// Creates a new DotSth instance, which writes into `document`.
+    pub fn new(document: &'d mut String) -> Result<DotSth<'d>> {
+        let mut markup = MarkupSth::new(document, Language::Other(dot_syntax()))?;
+        markup.set_formatter(Box::new(FnFormatter::from_fn(|state| {
+            match (&state.last.0, &state.next.0) {
+                (Sequence::Initial, _)
+                | (Sequence::Text, Sequence::Opening)
+                | (Sequence::Opening, Sequence::Closing) => FormatChanges::nothing(),
+                (Sequence::Opening, _) => {
+                    FormatChanges::lf_indent_more(state.indent, DEFAULT_INDENT)
+                }
+                (_, Sequence::Closing) => {
+                    FormatChanges::lf_indent_less(state.indent, DEFAULT_INDENT)
+                }
+                _ => FormatChanges::lf(),
+            }
+        })));
+        Ok(DotSth {
+            markup,
+            directed: None,
+        })
+    }
+
+    /// Begins a new graph, a `digraph`, if `directed` is set, otherwise a `graph`. An empty `name`
+    /// results in an anonymous graph. Graphs cannot be nested.
+    pub fn begin_graph(&mut self, directed: bool, name: &str) -> Result<()> {
+        if self.directed.is_some() {
+            return Err("DotSth: graphs cannot be nested".into());
+        }
+        let mut header = String::from(if directed { "digraph " } else { "graph " });
+        if !name.is_empty() {
+            write!(header, "{} ", dot_id(name))?;
+        }
+        self.markup.text_unchecked(&header)?;
+        self.markup.open("")?;
+        self.directed = Some(directed);
+        Ok(())
+    }
+
+    /// Inserts a node statement, e.g. `a [label="A"];`.
+    pub fn node(&mut self, id: &str, attrs: &[(&str, &str)]) -> Result<()> {
+        self.check_graph()?;
+        let statement = format!("{}{}", dot_id(id), dot_attrs(attrs));
+        self.markup.self_closing(&statement)
+    }
+
+    /// Inserts an edge statement, e.g. `a -> b;` in directed graphs or `a -- b;` in undirected ones.
+    pub fn edge(&mut self, from: &str, to: &str, attrs: &[(&str, &str)]) -> Result<()> {
+        let op = if self.check_graph()? { "->" } else { "--" };
+        let statement = format!("{} {} {}{}", dot_id(from), op, dot_id(to), dot_attrs(attrs));
+        self.markup.self_closing(&statement)
+    }
+
+    /// Ends the open graph.
+    pub fn end_graph(&mut self) -> Result<()> {
+        self.check_graph()?;
+        self.markup.close()?;
+        self.directed = None;
+        Ok(())
+    }
+
+    /// Finalizes the document. Returns an error, if a graph is still open.
+    pub fn finalize(self) -> Result<()> {
+        if self.directed.is_some() {
+            return Err("DotSth: graph has not been ended".into());
+        }
+        self.markup.finalize()
+    }
+
+    /// Internal check method, whether a graph is open. Returns whether it is directed.
+    fn check_graph(&self) -> Result<bool> {
+        self.directed
+            .ok_or_else(|| "DotSth: statements can only be inserted into a graph".into())
+    }
+}
+
+/// Internal syntax of DOT: Graphs are tag pairs with an empty tag name delimited by braces,
+/// statements are self-closing tags terminated by `;`.
+fn dot_syntax() -> SyntaxConfig {
+    SyntaxConfig {
+        doctype: None,
+        self_closing: Some(SelfClosingTagConfig {
+            before: Nothing,
+            after: Single(';'),
+        }),
+        tag_pairs: Some(TagPairConfig {
+            opening_before: Single('{'),
+            opening_after: Nothing,
+            closing_before: Single('}'),
+            closing_after: Nothing,
+        }),
+        properties: None,
+        text_escapes: Vec::new(),
+        attr_escapes: Vec::new(),
+    }
+}
+
+/// Internal method, which quotes an identifier, unless it is a plain alphanumeric one or a numeral.
+fn dot_id(id: &str) -> Cow<'_, str> {
+    let plain = id.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let numeral = !id.is_empty() && id.chars().all(|c| c.is_ascii_digit() || c == '.');
+    if plain || numeral {
+        Cow::Borrowed(id)
+    } else {
+        Cow::Owned(format!("\"{}\"", id.replace('"', "\\\"")))
+    }
+}
+
+/// Internal method, which formats an attribute list, e.g. ` [label="A", shape="box"]`.
+fn dot_attrs(attrs: &[(&str, &str)]) -> String {
+    if attrs.is_empty() {
+        return String::new();
+    }
+    let attrs: Vec<String> = attrs
+        .iter()
+        .map(|(name, value)| format!("{}=\"{}\"", dot_id(name), value.replace('"', "\\\"")))
+        .collect();
+    format!(" [{}]", attrs.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testfile;
+
+    #[test]
+    fn directed_graph_with_three_nodes() {
+        let mut document = String::new();
+        let mut dot = DotSth::new(&mut document).unwrap();
+        assert!(dot.node("a", &[]).is_err());
+        dot.begin_graph(true, "G").unwrap();
+        assert!(dot.begin_graph(true, "H").is_err());
+        dot.node("a", &[("label", "Start")]).unwrap();
+        dot.node("b", &[]).unwrap();
+        dot.node("end node", &[("label", "The \"End\""), ("shape", "box")])
+            .unwrap();
+        dot.edge("a", "b", &[]).unwrap();
+        dot.edge("b", "end node", &[("color", "red")]).unwrap();
+        dot.end_graph().unwrap();
+        dot.finalize().unwrap();
+
+        assert_eq!(document, testfile("dot_three_nodes.dot"));
+    }
+
+    #[test]
+    fn undirected_and_empty_graphs() {
+        let mut document = String::new();
+        let mut dot = DotSth::new(&mut document).unwrap();
+        dot.begin_graph(false, "").unwrap();
+        dot.end_graph().unwrap();
+        dot.begin_graph(false, "1").unwrap();
+        dot.edge("1", "2", &[]).unwrap();
+        dot.end_graph().unwrap();
+        dot.finalize().unwrap();
+        assert_eq!(document, "graph {}\ngraph 1 {\n    1 -- 2;\n}");
+
+        let mut document = String::new();
+        let mut dot = DotSth::new(&mut document).unwrap();
+        dot.begin_graph(false, "").unwrap();
+        assert!(dot.finalize().is_err());
+    }
+}
@@ -47,6 +47,23 @@ use crate::Result;
 /// Crate default and initial indenting step size. Can be overwritten by trait methods.
 pub const DEFAULT_INDENT: usize = 4;
 
+/// Returns the display width of a string in columns, as used for column-based formatting such as
+/// text wrapping.
+///
+/// With the default feature `unicode-width` enabled, wide characters (e.g. CJK) count as two
+/// columns and zero-width characters count as none. Without this feature, every `char` counts as
+/// one column.
+pub fn display_width(s: &str) -> usize {
+    #[cfg(feature = "unicode-width")]
+    {
+        unicode_width::UnicodeWidthStr::width(s)
+    }
+    #[cfg(not(feature = "unicode-width"))]
+    {
+        s.chars().count()
+    }
+}
+
 /// Defines the type of a sequence (tags, text and linefeeds) from perspective of a formatter.
 ///
 /// A Markup Language can have tag pair elements, self-closing elements, some initial header tag,
@@ -69,7 +86,7 @@ pub enum Sequence {
 }
 
 /// Pendant to the raw `Sequence`, but combined with a `String` to differ between various tags.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TagSequence(pub Sequence, pub String);
 
 impl<'s> TagSequence {
@@ -217,6 +234,12 @@ impl SequenceState {
     pub(crate) fn text_close(last: &str) -> SequenceState {
         Self::teststate(TagSequence::text(), TagSequence::closing(last))
     }
+
+    /// Only for testing purposes used internally.
+    #[cfg(test)]
+    pub(crate) fn text_open(next: &str) -> SequenceState {
+        Self::teststate(TagSequence::text(), TagSequence::opening(next))
+    }
 }
 
 impl Default for SequenceState {
@@ -325,15 +348,54 @@ pub trait Formatter: std::fmt::Debug {
         DEFAULT_INDENT
     }
 
+    /// Modify and set the optional width for wrapping properties. If the properties of a tag
+    /// would exceed this width, each property will be placed on its own line, aligned to the
+    /// first one. Default is `None`, no wrapping.
+    fn set_attr_wrap_width(&mut self, _width: Option<usize>) {}
+
+    /// Returns the optional width for wrapping properties.
+    fn get_attr_wrap_width(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns whether this formatter never applies any changes, so `MarkupSth` can skip calling
+    /// `check()` at all. Default is `false`.
+    fn is_noop(&self) -> bool {
+        false
+    }
+
     /// Whatever may configurable and may have been re-configured, this function shall reset all
     /// configurable properties back to their defaults.
     fn reset_to_defaults(&mut self) {}
 
+    /// Resets the internal, operational state, e.g. a stack of open tags, but keeps everything
+    /// configured. Called by `MarkupSth::reset()` before a new document gets started.
+    fn reset_state(&mut self) {}
+
+    /// Takes over the operational state of a document in progress, i.e. its open tags and the
+    /// current indenting, when this formatter gets set in the middle of a document. Called by
+    /// `MarkupSth::set_formatter()` and `MarkupSth::with_formatter_scope()`.
+    fn sync_state(&mut self, _state: &SequenceState) {}
+
     /// The core function of this crate's general concept. It shall check for optional format
     /// changes between the last inserted tag and the next one, before it will get inserted into
     /// the document under edit.
     fn check(&mut self, state: &SequenceState) -> FormatChanges;
 
+    /// Optional hook, called once at the start of the document, before anything else (even the
+    /// doctype) gets written. The returned string will be inserted into the document as it is,
+    /// e.g. a banner comment with some generation metadata.
+    fn on_document_start(&mut self) -> Option<String> {
+        None
+    }
+
+    /// Optional hook, called once at the end of the document in `MarkupSth::finalize()`, after
+    /// the last operation has been finalized. The returned string will be appended to the
+    /// document as it is, e.g. a summary comment.
+    fn on_document_end(&mut self) -> Option<String> {
+        None
+    }
+
     /// Returns this special kind of Formatter.
     fn get_ext_auto_indenting(&mut self) -> Option<&mut dyn ExtAutoIndenting> {
         None
@@ -363,4 +425,10 @@ pub trait ExtAutoIndenting: Formatter {
 
     /// Shall reset and empty all registers for fixed rules.
     fn reset_ruleset(&mut self) -> Result<()>;
+
+    /// Returns the registers for the rules indent-always, LF-always and LF-closing, e.g. to assert
+    /// or log the configuration. Default returns empty registers.
+    fn ruleset(&self) -> (&[String], &[String], &[String]) {
+        (&[], &[], &[])
+    }
 }
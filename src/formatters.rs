@@ -1,7 +1,8 @@
 //! Module contains the pre-implemented formatters of this crate. Suche as the `NoFormatting`, the
-//! `AlwaysIndentAlwaysLf`, and the `AutoIndent`, which are ready to be used without any effort.
+//! `AlwaysIndentAlwaysLf`, the `AutoIndent`, and the `HtmlBlockIndent`, which are ready to be used
+//! without any effort.
 //!
-//! All of the three pre-implemented formatters implement for sure the trait `Formatter`. Formatter
+//! All of the pre-implemented formatters implement for sure the trait `Formatter`. Formatter
 //! `AutoIndent` implements the additional feature trait `AutoFmtRuleset` as well. Have a look at
 //! their further documentation to get an overview.
 //!
@@ -67,8 +68,59 @@
 //!     AutoFmtRule::LfClosing
 //!     ).unwrap();
 //! ```
+//!
+//! ### `HtmlBlockIndent`
+//!
+//! A pre-implemented formatter for HTML, which requires no configuration at all.
+//!
+//! It ships with a sensible set of block-level tags, which indent their content, and tags, which
+//! are placed on their own line. All other tags are treated as inline tags without formatting.
+//!
+//! ### `BlockInline`
+//!
+//! A pre-implemented formatter, which is configured by two sets of tags only: Block tags indent
+//! their content and are placed on their own line, everything else is formatted inline.
+//!
+//! ### `DiffFriendly`
+//!
+//! A pre-implemented formatter for minimal diffs of generated files, e.g. in code reviews. Like
+//! `AlwaysIndentAlwaysLf`, but also text is placed on its own line and each property of a tag is
+//! placed on its own line.
+//!
+//! ### `AlignClosing`
+//!
+//! A pre-implemented formatter, which places every tag on its own line and aligns closing tags
+//! under their opening tags, whenever the content spans multiple lines. Text stays in line.
+//!
+//! ### `Canonical`
+//!
+//! A pre-implemented formatter for canonical output, which places each tag on its own line without
+//! any indenting. Combined with `MarkupSth::sort_attributes`, the output is stable and diffable.
+//!
+//! ### `FnFormatter`
+//!
+//! A formatter, which delegates to a closure, for prototyping formatting rules without
+//! implementing a new type.
+//!
+//! ### `MaxBlankLines`
+//!
+//! A wrapper around any other formatter, which suppresses its linefeeds, whenever they would
+//! create more than a maximum number of consecutive blank lines.
 
-use crate::{format::*, Result};
+use crate::{format::*, syntax::Language, Result};
+use std::collections::HashMap;
+
+/// Returns the default formatter of `MarkupSth` for the Markup Language `ml`. HTML and XML get an
+/// `AutoIndent`, which can be configured by `Formatter::get_ext_auto_indenting()`. It is empty
+/// initially for HTML, because there are many tastes in HTML, and configured by `AutoIndent::xml()`
+/// for XML. Other languages get `NoFormatting`, because nothing can be assumed about their layout.
+pub fn default_formatter(ml: &Language) -> Box<dyn Formatter> {
+    match ml {
+        Language::Html | Language::Html5 | Language::XHtml => Box::new(AutoIndent::new()),
+        Language::Xml => Box::new(AutoIndent::xml()),
+        Language::Other(_) => Box::new(NoFormatting::new()),
+    }
+}
 
 /// A pre-implemented formatter for having no formatting at all. No linefeeds, no indenting at all.
 ///
@@ -82,6 +134,10 @@ impl Formatter for NoFormatting {
         NoFormatting
     }
 
+    fn is_noop(&self) -> bool {
+        true
+    }
+
     fn check(&mut self, _: &SequenceState) -> FormatChanges {
         FormatChanges::nothing()
     }
@@ -92,11 +148,11 @@ impl Formatter for NoFormatting {
 /// You want to have the clearest readable Markup file you can imagine, then this formatter is
 /// yours. Output files may be suitable for debugging and error search, but maybe too pendantic.
 #[derive(Debug)]
-pub struct AlwaysIndentAlwaysLf(usize);
+pub struct AlwaysIndentAlwaysLf(usize, Option<usize>);
 
 impl Formatter for AlwaysIndentAlwaysLf {
     fn new() -> AlwaysIndentAlwaysLf {
-        AlwaysIndentAlwaysLf(DEFAULT_INDENT)
+        AlwaysIndentAlwaysLf(DEFAULT_INDENT, None)
     }
 
     fn set_indent_step_size(&mut self, step_size: usize) {
@@ -107,8 +163,17 @@ impl Formatter for AlwaysIndentAlwaysLf {
         self.0
     }
 
+    fn set_attr_wrap_width(&mut self, width: Option<usize>) {
+        self.1 = width;
+    }
+
+    fn get_attr_wrap_width(&self) -> Option<usize> {
+        self.1
+    }
+
     fn reset_to_defaults(&mut self) {
         self.0 = DEFAULT_INDENT;
+        self.1 = None;
     }
 
     fn check(&mut self, state: &SequenceState) -> FormatChanges {
@@ -168,13 +233,72 @@ pub struct AutoIndent {
     pub fltr_lf_always: Vec<String>,
     /// List for tags, where a LINEFEED shall inserted after closing tags.
     pub fltr_lf_closing: Vec<String>,
+    /// List for inline tags, whose text content always stays on the same line as the opening and
+    /// closing tag, regardless of the rules above.
+    pub inline_tags: Vec<String>,
     /// Internal, operational, for tracking whether indented or not.
     indent_stack: Vec<BlockClosingOp>,
     /// The indenting step size.
     indent_step: usize,
+    /// Indenting step sizes of certain tags, which differ from `indent_step`.
+    tag_indent_steps: HashMap<String, usize>,
+    /// Optional width for wrapping properties.
+    attr_wrap_width: Option<usize>,
 }
 
 impl AutoIndent {
+    /// A tag name, which matches every tag, when added to a rule or to `inline_tags`. It is no
+    /// valid tag name in any supported Markup Language.
+    pub const ANY_TAG: &'static str = "*";
+
+    /// Creates an `AutoIndent` configured for XML, which has no common set of tags: Every element
+    /// indents its content and is placed on its own line, but text content and empty elements stay
+    /// in line, e.g. `<root>`, `<item>Text</item>`, `<empty></empty>` and `</root>` on separate
+    /// lines.
+    pub fn xml() -> AutoIndent {
+        AutoIndent::with_tag_kinds(&[Self::ANY_TAG], &[], &[Self::ANY_TAG])
+    }
+
+    /// Creates an `AutoIndent` configured for HTML: Tags of `HTML_BLOCK_TAGS` indent their
+    /// content, tags of `HTML_LINE_TAGS` are placed on their own line and tags of
+    /// `HTML_INLINE_TAGS` keep their text in line.
+    pub fn html() -> AutoIndent {
+        AutoIndent::with_tag_kinds(HTML_BLOCK_TAGS, HTML_LINE_TAGS, HTML_INLINE_TAGS)
+    }
+
+    /// Creates an `AutoIndent` configured by kinds of tags instead of rules: Tags of `block`
+    /// indent their content and are placed on their own line (rules Indent-Always and LF-Closing),
+    /// tags of `line` are placed on their own line (rule LF-Closing) and tags of `inline` keep
+    /// their text in line (see `inline_tags`).
+    pub fn with_tag_kinds<S: AsRef<str>>(block: &[S], line: &[S], inline: &[S]) -> AutoIndent {
+        let mut fmtr = AutoIndent::new();
+        fmtr.set_tag_kinds(block, line, inline);
+        fmtr
+    }
+
+    /// Internal method, which replaces the ruleset and the inline tags by kinds of tags, see
+    /// `with_tag_kinds()`.
+    fn set_tag_kinds<S: AsRef<str>>(&mut self, block: &[S], line: &[S], inline: &[S]) {
+        self.fltr_indent_always = to_strings(block).collect();
+        self.fltr_lf_always.clear();
+        self.fltr_lf_closing = to_strings(block).chain(to_strings(line)).collect();
+        self.inline_tags = to_strings(inline).collect();
+    }
+
+    /// Sets an individual indenting step size for the content of tag `tag`, which will be used
+    /// instead of the general indenting step size.
+    pub fn set_tag_indent_step(&mut self, tag: &str, step: usize) {
+        self.tag_indent_steps.insert(tag.to_string(), step);
+    }
+
+    /// Internal method, which returns the indenting step size for the content of `tag`.
+    fn indent_step_of(&self, tag: &str) -> usize {
+        self.tag_indent_steps
+            .get(tag)
+            .copied()
+            .unwrap_or(self.indent_step)
+    }
+
     // Internal method to check if tags are in another filter too.
     fn check_other_filter(
         &self,
@@ -210,13 +334,20 @@ impl AutoIndent {
             AutoFmtRule::LfClosing => &self.fltr_lf_closing,
         };
         for tf in fltr.iter() {
-            if tf == &tagseq.1 {
+            if tf == &tagseq.1 || tf == Self::ANY_TAG {
                 return true;
             }
         }
         false
     }
 
+    /// Internal check method, if the text content of `tag` stays in line.
+    fn is_inline(&self, tag: &str) -> bool {
+        self.inline_tags
+            .iter()
+            .any(|t| t == tag || t == Self::ANY_TAG)
+    }
+
     /// Internal check method, if tag is contained in filter `fltr` and of type `seq`.
     fn is_ts_in_fltr_aot(&self, tagseq: &TagSequence, fltr: AutoFmtRule, seq: Sequence) -> bool {
         if tagseq.0 != seq {
@@ -232,8 +363,11 @@ impl Formatter for AutoIndent {
             fltr_indent_always: Vec::new(),
             fltr_lf_always: Vec::new(),
             fltr_lf_closing: Vec::new(),
+            inline_tags: Vec::new(),
             indent_stack: Vec::new(),
             indent_step: DEFAULT_INDENT,
+            tag_indent_steps: HashMap::new(),
+            attr_wrap_width: None,
         }
     }
 
@@ -245,11 +379,50 @@ impl Formatter for AutoIndent {
         self.indent_step
     }
 
+    fn set_attr_wrap_width(&mut self, width: Option<usize>) {
+        self.attr_wrap_width = width;
+    }
+
+    fn get_attr_wrap_width(&self) -> Option<usize> {
+        self.attr_wrap_width
+    }
+
     fn reset_to_defaults(&mut self) {
         self.fltr_indent_always.clear();
         self.fltr_lf_always.clear();
         self.fltr_lf_closing.clear();
+        self.inline_tags.clear();
         self.indent_step = DEFAULT_INDENT;
+        self.tag_indent_steps.clear();
+        self.attr_wrap_width = None;
+    }
+
+    fn reset_state(&mut self) {
+        self.indent_stack.clear();
+    }
+
+    fn sync_state(&mut self, state: &SequenceState) {
+        // The closing-instruction of a just opened tag gets pushed by the next check.
+        let mut open = state.tag_stack.len();
+        if matches!(state.last.0, Sequence::Opening) {
+            open = open.saturating_sub(1);
+        }
+        let mut indent = state.indent;
+        self.indent_stack.clear();
+        for tag in state.tag_stack[..open].iter() {
+            let tagseq = TagSequence::opening(tag);
+            let closing_op = if self.is_ts_in_filter(&tagseq, AutoFmtRule::IndentAlways) {
+                // The indenting of the document must not be undone more than it has been done.
+                let step = self.indent_step_of(tag).min(indent);
+                indent -= step;
+                BlockClosingOp::LfIndentLess(step)
+            } else if self.is_ts_in_filter(&tagseq, AutoFmtRule::LfAlways) {
+                BlockClosingOp::Linefeed
+            } else {
+                BlockClosingOp::Nothing
+            };
+            self.indent_stack.push(closing_op);
+        }
     }
 
     fn get_ext_auto_indenting(&mut self) -> Option<&mut dyn ExtAutoIndenting> {
@@ -259,6 +432,19 @@ impl Formatter for AutoIndent {
     fn check(&mut self, state: &SequenceState) -> FormatChanges {
         let mut changes = FormatChanges::nothing();
 
+        // Text content of inline tags always stays in line.
+        match (&state.last.0, &state.next.0) {
+            (Sequence::Opening, Sequence::Text) if self.is_inline(&state.last.1) => {
+                self.indent_stack.push(BlockClosingOp::Nothing);
+                return changes;
+            }
+            (Sequence::Text, Sequence::Closing) if self.is_inline(&state.next.1) => {
+                self.indent_stack.pop();
+                return changes;
+            }
+            _ => {}
+        }
+
         let lf_always = self.is_ts_in_filter(&state.last, AutoFmtRule::LfAlways);
         let ind_always = self.is_ts_in_filter(&state.last, AutoFmtRule::IndentAlways);
 
@@ -267,20 +453,21 @@ impl Formatter for AutoIndent {
             // because of optional less-indenting.
             if matches!(state.last.0, Sequence::Opening) {
                 // if: detect the rare case <open></close>
-                // In case of LF-Always or Indent-Always insert a line feed.
-                if lf_always || ind_always {
+                // In case of LF-Always or Indent-Always insert a line feed, unless it is inline.
+                if (lf_always || ind_always) && !self.is_inline(&state.last.1) {
                     changes = FormatChanges::lf();
                 }
             } else {
-                // Pop a closing-instruction from the stack, there must be one for this closing!!
-                let closing_op = self.indent_stack.pop().unwrap();
+                // Pop a closing-instruction from the stack. The tag may have been opened before
+                // this formatter was in charge, e.g. in a formatter scope, so nothing to undo then.
+                let closing_op = self.indent_stack.pop().unwrap_or(BlockClosingOp::Nothing);
 
                 if matches!(closing_op, BlockClosingOp::Linefeed) {
                     // if: check if we do line feeds.
                     changes = FormatChanges::lf();
-                } else if matches!(closing_op, BlockClosingOp::LfIndentLess) {
+                } else if let BlockClosingOp::LfIndentLess(step) = closing_op {
                     // if: check if we do a block-finishing, (LF + less indenting).
-                    changes = FormatChanges::lf_indent_less(state.indent, self.indent_step);
+                    changes = FormatChanges::lf_indent_less(state.indent, step);
                 } else if self.is_ts_in_fltr_aot(
                     &state.last,
                     AutoFmtRule::LfClosing,
@@ -303,20 +490,21 @@ impl Formatter for AutoIndent {
                     // if: After an opening-tag LINEFEED and optional indenting can be desired.
                     // Anyway, for each opening tag we add a flag for indenting on the internal
                     // stack.
+                    let step = self.indent_step_of(&state.last.1);
                     if matches!(state.next.0, Sequence::LineFeed) {
                         if lf_always {
                             changes = FormatChanges::lf();
                             self.indent_stack.push(BlockClosingOp::Linefeed);
                         } else if ind_always {
-                            changes = FormatChanges::lf_indent_more(state.indent, self.indent_step);
-                            self.indent_stack.push(BlockClosingOp::LfIndentLess);
+                            changes = FormatChanges::lf_indent_more(state.indent, step);
+                            self.indent_stack.push(BlockClosingOp::LfIndentLess(step));
                         } else {
-                            changes = FormatChanges::indent_more(state.indent, self.indent_step);
-                            self.indent_stack.push(BlockClosingOp::LfIndentLess);
+                            changes = FormatChanges::indent_more(state.indent, step);
+                            self.indent_stack.push(BlockClosingOp::LfIndentLess(step));
                         }
                     } else if ind_always {
-                        self.indent_stack.push(BlockClosingOp::LfIndentLess);
-                        changes = FormatChanges::lf_indent_more(state.indent, self.indent_step);
+                        self.indent_stack.push(BlockClosingOp::LfIndentLess(step));
+                        changes = FormatChanges::lf_indent_more(state.indent, step);
                     } else if lf_always {
                         self.indent_stack.push(BlockClosingOp::Linefeed);
                         changes = FormatChanges::lf();
@@ -325,23 +513,22 @@ impl Formatter for AutoIndent {
                         changes = FormatChanges::nothing();
                     }
                 }
-                Sequence::Closing => {
-                    // After a closing-tag a LINEFEED can be desired
+                // After a closing-tag a LINEFEED can be desired
+                Sequence::Closing
                     if self.is_ts_in_filter(&state.last, AutoFmtRule::IndentAlways)
                         || self.is_ts_in_filter(&state.last, AutoFmtRule::LfAlways)
-                        || self.is_ts_in_filter(&state.last, AutoFmtRule::LfClosing)
-                    {
-                        changes = FormatChanges::lf();
-                    }
+                        || self.is_ts_in_filter(&state.last, AutoFmtRule::LfClosing) =>
+                {
+                    changes = FormatChanges::lf();
                 }
-                Sequence::SelfClosing => {
+                Sequence::SelfClosing
                     if self.is_ts_in_fltr_aot(
                         &state.last,
                         AutoFmtRule::LfClosing,
                         Sequence::SelfClosing,
-                    ) {
-                        changes = FormatChanges::lf();
-                    }
+                    ) =>
+                {
+                    changes = FormatChanges::lf();
                 }
                 Sequence::Initial => {
                     // If last tag was the initial document sequence, also line feed always!
@@ -380,6 +567,575 @@ impl ExtAutoIndenting for AutoIndent {
         self.fltr_lf_closing.clear();
         Ok(())
     }
+
+    fn ruleset(&self) -> (&[String], &[String], &[String]) {
+        (
+            &self.fltr_indent_always,
+            &self.fltr_lf_always,
+            &self.fltr_lf_closing,
+        )
+    }
+}
+
+/// Default tags of `HtmlBlockIndent`, which indent their content.
+pub const HTML_BLOCK_TAGS: &[&str] = &[
+    "html",
+    "head",
+    "body",
+    "header",
+    "nav",
+    "main",
+    "section",
+    "article",
+    "aside",
+    "footer",
+    "div",
+    "ul",
+    "ol",
+    "dl",
+    "table",
+    "thead",
+    "tbody",
+    "tfoot",
+    "tr",
+    "form",
+    "fieldset",
+    "blockquote",
+    "figure",
+];
+
+/// Default tags of `HtmlBlockIndent`, which will be placed on their own line, but keep their
+/// content in line.
+pub const HTML_LINE_TAGS: &[&str] = &[
+    "title",
+    "meta",
+    "link",
+    "base",
+    "script",
+    "style",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "p",
+    "li",
+    "dt",
+    "dd",
+    "th",
+    "td",
+    "caption",
+    "figcaption",
+    "legend",
+    "hr",
+    "pre",
+];
+
+/// Common inline tags of HTML, whose text stays in line, see `AutoIndent::html()`.
+pub const HTML_INLINE_TAGS: &[&str] = &[
+    "a", "abbr", "b", "cite", "code", "em", "i", "kbd", "label", "mark", "q", "s", "small", "span",
+    "strong", "sub", "sup", "time", "u",
+];
+
+/// Kind of a tag for the formatter `HtmlBlockIndent`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HtmlTagKind {
+    /// Block-level tag, which indents its content, e.g. `<section>`.
+    Block,
+    /// Block-level tag on its own line, which keeps its content in line, e.g. `<p>`.
+    Line,
+    /// Inline tag without any formatting, e.g. `<span>`. All unknown tags are inline tags.
+    Inline,
+}
+
+/// A pre-implemented formatter for HTML, which requires no configuration at all.
+///
+/// This formatter knows a sensible set of block-level HTML tags (`div`, `section`, `ul`, `table`,
+/// ...), which will indent their content, and a set of block-level tags which will be placed on
+/// their own line but keep their content in line (`p`, `li`, `h1`, ...). All other tags, e.g.
+/// `span`, `a` or `strong`, are inline tags without any formatting. Internally it is an
+/// `AutoIndent`, configured by these sets. The sets can be modified by
+/// `HtmlBlockIndent::set_tag_kind()`, for example:
+/// ```
+/// use markupsth::{Formatter, HtmlBlockIndent, HtmlTagKind, Language, MarkupSth};
+///
+/// let mut fmtr = HtmlBlockIndent::new();
+/// fmtr.set_tag_kind(&["li"], HtmlTagKind::Block);
+/// fmtr.set_tag_kind(&["figure"], HtmlTagKind::Inline);
+///
+/// let mut doc = String::new();
+/// let mut mus = MarkupSth::new(&mut doc, Language::Html).unwrap();
+/// mus.set_formatter(Box::new(fmtr));
+/// ```
+#[derive(Debug)]
+pub struct HtmlBlockIndent {
+    /// Tags, which indent their content.
+    block_tags: Vec<String>,
+    /// Tags, which will be placed on their own line.
+    line_tags: Vec<String>,
+    /// The internal formatter, configured by the sets of tags.
+    auto_indent: AutoIndent,
+}
+
+impl HtmlBlockIndent {
+    /// Changes the kind of all given tags to `kind`.
+    pub fn set_tag_kind(&mut self, tags: &[&str], kind: HtmlTagKind) {
+        self.block_tags.retain(|t| !tags.contains(&t.as_str()));
+        self.line_tags.retain(|t| !tags.contains(&t.as_str()));
+        let tags = tags.iter().map(|t| t.to_string());
+        match kind {
+            HtmlTagKind::Block => self.block_tags.extend(tags),
+            HtmlTagKind::Line => self.line_tags.extend(tags),
+            HtmlTagKind::Inline => {}
+        }
+        self.update_ruleset();
+    }
+
+    /// Returns the kind of tag `tag`.
+    pub fn tag_kind(&self, tag: &str) -> HtmlTagKind {
+        if self.block_tags.iter().any(|t| t == tag) {
+            HtmlTagKind::Block
+        } else if self.line_tags.iter().any(|t| t == tag) {
+            HtmlTagKind::Line
+        } else {
+            HtmlTagKind::Inline
+        }
+    }
+
+    /// Internal method to apply the sets of tags to the internal `AutoIndent`.
+    fn update_ruleset(&mut self) {
+        self.auto_indent
+            .set_tag_kinds(&self.block_tags, &self.line_tags, &[]);
+    }
+}
+
+impl Formatter for HtmlBlockIndent {
+    fn new() -> HtmlBlockIndent {
+        let mut fmtr = HtmlBlockIndent {
+            block_tags: HTML_BLOCK_TAGS.iter().map(|t| t.to_string()).collect(),
+            line_tags: HTML_LINE_TAGS.iter().map(|t| t.to_string()).collect(),
+            auto_indent: AutoIndent::new(),
+        };
+        fmtr.update_ruleset();
+        fmtr
+    }
+
+    fn set_indent_step_size(&mut self, step_size: usize) {
+        self.auto_indent.set_indent_step_size(step_size);
+    }
+
+    fn get_indent_step_size(&self) -> usize {
+        self.auto_indent.get_indent_step_size()
+    }
+
+    fn set_attr_wrap_width(&mut self, width: Option<usize>) {
+        self.auto_indent.set_attr_wrap_width(width);
+    }
+
+    fn get_attr_wrap_width(&self) -> Option<usize> {
+        self.auto_indent.get_attr_wrap_width()
+    }
+
+    fn reset_to_defaults(&mut self) {
+        self.block_tags = HTML_BLOCK_TAGS.iter().map(|t| t.to_string()).collect();
+        self.line_tags = HTML_LINE_TAGS.iter().map(|t| t.to_string()).collect();
+        self.auto_indent.reset_to_defaults();
+        self.update_ruleset();
+    }
+
+    fn reset_state(&mut self) {
+        self.auto_indent.reset_state();
+    }
+
+    fn sync_state(&mut self, state: &SequenceState) {
+        self.auto_indent.sync_state(state);
+    }
+
+    fn check(&mut self, state: &SequenceState) -> FormatChanges {
+        self.auto_indent.check(state)
+    }
+}
+
+/// A pre-implemented formatter, which is configured by a set of block tags and a set of inline
+/// tags.
+///
+/// Block tags indent their content and get linefeeds around them, inline tags keep their text in
+/// line and produce no formatting, like all other tags. This is a simpler alternative to the three
+/// rules of `AutoIndent`, to which it delegates internally.
+///
+/// ```
+/// use markupsth::{BlockInline, Language, MarkupSth};
+///
+/// let mut doc = String::new();
+/// let mut mus = MarkupSth::new(&mut doc, Language::Html).unwrap();
+/// mus.set_formatter(Box::new(BlockInline::with_tags(&["body", "div"], &["a", "b"])));
+/// ```
+///
+/// Constructed by `Formatter::new()`, both sets are empty.
+#[derive(Debug)]
+pub struct BlockInline {
+    /// Tags, which indent their content.
+    block_tags: Vec<String>,
+    /// Tags, which keep their text in line.
+    inline_tags: Vec<String>,
+    /// The internal formatter, configured by the sets of tags.
+    auto_indent: AutoIndent,
+}
+
+impl BlockInline {
+    /// Creates a `BlockInline` with block tags `block` and inline tags `inline`.
+    pub fn with_tags(block: &[&str], inline: &[&str]) -> BlockInline {
+        let mut fmtr = BlockInline {
+            block_tags: block.iter().map(|t| t.to_string()).collect(),
+            inline_tags: inline.iter().map(|t| t.to_string()).collect(),
+            auto_indent: AutoIndent::new(),
+        };
+        fmtr.update_ruleset();
+        fmtr
+    }
+
+    /// Internal method to apply the sets of tags to the internal `AutoIndent`.
+    fn update_ruleset(&mut self) {
+        self.auto_indent
+            .set_tag_kinds(&self.block_tags, &[], &self.inline_tags);
+    }
+}
+
+impl Formatter for BlockInline {
+    fn new() -> BlockInline {
+        BlockInline::with_tags(&[], &[])
+    }
+
+    fn set_indent_step_size(&mut self, step_size: usize) {
+        self.auto_indent.set_indent_step_size(step_size);
+    }
+
+    fn get_indent_step_size(&self) -> usize {
+        self.auto_indent.get_indent_step_size()
+    }
+
+    fn set_attr_wrap_width(&mut self, width: Option<usize>) {
+        self.auto_indent.set_attr_wrap_width(width);
+    }
+
+    fn get_attr_wrap_width(&self) -> Option<usize> {
+        self.auto_indent.get_attr_wrap_width()
+    }
+
+    fn reset_to_defaults(&mut self) {
+        // The sets of tags are part of the construction, not of the configuration.
+        self.auto_indent.reset_to_defaults();
+        self.update_ruleset();
+    }
+
+    fn reset_state(&mut self) {
+        self.auto_indent.reset_state();
+    }
+
+    fn sync_state(&mut self, state: &SequenceState) {
+        self.auto_indent.sync_state(state);
+    }
+
+    fn check(&mut self, state: &SequenceState) -> FormatChanges {
+        self.auto_indent.check(state)
+    }
+}
+
+/// A pre-implemented formatter for minimal diffs of generated files, e.g. in code reviews.
+///
+/// Like `AlwaysIndentAlwaysLf`, every element is placed on its own line and indented strictly, but
+/// also text is placed on its own line. Additionally, each property of a tag is placed on its own
+/// line, aligned to the first one, so adding or changing a property only changes a single line.
+#[derive(Debug)]
+pub struct DiffFriendly(AlwaysIndentAlwaysLf);
+
+impl Formatter for DiffFriendly {
+    fn new() -> DiffFriendly {
+        DiffFriendly(AlwaysIndentAlwaysLf::new())
+    }
+
+    fn set_indent_step_size(&mut self, step_size: usize) {
+        self.0.set_indent_step_size(step_size);
+    }
+
+    fn get_indent_step_size(&self) -> usize {
+        self.0.get_indent_step_size()
+    }
+
+    fn get_attr_wrap_width(&self) -> Option<usize> {
+        // Any width will be exceeded, so each property is placed on its own line.
+        Some(0)
+    }
+
+    fn reset_to_defaults(&mut self) {
+        self.0.reset_to_defaults();
+    }
+
+    fn check(&mut self, state: &SequenceState) -> FormatChanges {
+        match (&state.last.0, &state.next.0) {
+            (Sequence::Text, Sequence::Opening | Sequence::SelfClosing | Sequence::Text) => {
+                FormatChanges::lf()
+            }
+            _ => self.0.check(state),
+        }
+    }
+}
+
+/// A pre-implemented formatter, which aligns closing tags under their opening tags.
+///
+/// Every tag is placed on its own line, child elements get indented. Text stays in line with its
+/// surrounding tags. Whenever the content of a tag pair spans multiple lines, its closing tag will
+/// be placed on its own line at the column of the opening tag, otherwise it stays in line.
+#[derive(Debug)]
+pub struct AlignClosing {
+    /// The indenting step size.
+    indent_step: usize,
+    /// Internal, operational, indenting of each open tag and whether its content spans lines.
+    open_stack: Vec<(usize, bool)>,
+}
+
+impl Formatter for AlignClosing {
+    fn new() -> AlignClosing {
+        AlignClosing {
+            indent_step: DEFAULT_INDENT,
+            open_stack: Vec::new(),
+        }
+    }
+
+    fn set_indent_step_size(&mut self, step_size: usize) {
+        self.indent_step = step_size;
+    }
+
+    fn get_indent_step_size(&self) -> usize {
+        self.indent_step
+    }
+
+    fn reset_to_defaults(&mut self) {
+        self.indent_step = DEFAULT_INDENT;
+        self.open_stack.clear();
+    }
+
+    fn reset_state(&mut self) {
+        self.open_stack.clear();
+    }
+
+    fn check(&mut self, state: &SequenceState) -> FormatChanges {
+        match state.next.0 {
+            Sequence::Opening | Sequence::SelfClosing => {
+                let changes = match state.last.0 {
+                    Sequence::Initial | Sequence::LineFeed | Sequence::Text => {
+                        FormatChanges::nothing()
+                    }
+                    Sequence::Opening => {
+                        FormatChanges::lf_indent_more(state.indent, self.indent_step)
+                    }
+                    _ => FormatChanges::lf(),
+                };
+                if changes.new_line {
+                    if let Some(parent) = self.open_stack.last_mut() {
+                        parent.1 = true;
+                    }
+                }
+                if state.next.0 == Sequence::Opening {
+                    let indent = changes.new_indent.unwrap_or(state.indent);
+                    self.open_stack.push((indent, false));
+                }
+                changes
+            }
+            Sequence::Closing => match self.open_stack.pop() {
+                Some((indent, true)) => FormatChanges {
+                    new_line: true,
+                    new_indent: Some(indent),
+                },
+                _ => FormatChanges::nothing(),
+            },
+            _ => FormatChanges::nothing(),
+        }
+    }
+}
+
+/// A pre-implemented formatter for canonical output, e.g. to compare XML documents.
+///
+/// Each tag is placed on its own line without any indenting, so whitespace is always the same for
+/// the same structure. Text stays in line with its tags. Enable `MarkupSth::sort_attributes` in
+/// addition to get properties in a stable order.
+#[derive(Debug)]
+pub struct Canonical;
+
+impl Formatter for Canonical {
+    fn new() -> Canonical {
+        Canonical
+    }
+
+    fn check(&mut self, state: &SequenceState) -> FormatChanges {
+        match (&state.last.0, &state.next.0) {
+            (Sequence::Initial, _)
+            | (_, Sequence::Text)
+            | (Sequence::Text, Sequence::Closing)
+            | (Sequence::Opening, Sequence::Closing) => FormatChanges::nothing(),
+            _ => FormatChanges::lf(),
+        }
+    }
+}
+
+/// A formatter, which delegates `check()` to a closure, for custom rules without a new type.
+///
+/// ```
+/// use markupsth::{
+///     format::{FormatChanges, Sequence},
+///     FnFormatter, Language, MarkupSth,
+/// };
+///
+/// let mut document = String::new();
+/// let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+/// mus.set_formatter(Box::new(FnFormatter::from_fn(|state| {
+///     FormatChanges::may_lf(state.last.0 == Sequence::Closing)
+/// })));
+/// ```
+///
+/// Constructed by `Formatter::new()`, the closure does not apply any formatting.
+pub struct FnFormatter(Box<dyn FnMut(&SequenceState) -> FormatChanges>);
+
+impl FnFormatter {
+    /// Creates a `FnFormatter`, which delegates `check()` to closure `f`.
+    pub fn from_fn<F>(f: F) -> FnFormatter
+    where
+        F: FnMut(&SequenceState) -> FormatChanges + 'static,
+    {
+        FnFormatter(Box::new(f))
+    }
+}
+
+impl std::fmt::Debug for FnFormatter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FnFormatter")
+    }
+}
+
+impl Formatter for FnFormatter {
+    fn new() -> FnFormatter {
+        FnFormatter(Box::new(|_| FormatChanges::nothing()))
+    }
+
+    fn check(&mut self, state: &SequenceState) -> FormatChanges {
+        (self.0)(state)
+    }
+}
+
+/// A wrapper around another formatter, which limits the number of consecutive blank lines.
+///
+/// Everything is delegated to the inner formatter, but linefeeds of the inner formatter are
+/// suppressed, whenever they would create more than `max` consecutive blank lines. Manual
+/// linefeeds (see `MarkupSth::new_line()`) are always inserted, but count to the run of blank
+/// lines, e.g. when composing fragments, which each add trailing linefeeds.
+///
+/// ```
+/// use markupsth::{AlwaysIndentAlwaysLf, Formatter, Language, MarkupSth, MaxBlankLines};
+///
+/// let mut document = String::new();
+/// let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+/// mus.set_formatter(Box::new(MaxBlankLines::wrap(
+///     Box::new(AlwaysIndentAlwaysLf::new()),
+///     0,
+/// )));
+/// ```
+///
+/// Constructed by `Formatter::new()`, it wraps an `AlwaysIndentAlwaysLf` and allows one blank
+/// line.
+#[derive(Debug)]
+pub struct MaxBlankLines {
+    /// The wrapped formatter.
+    inner: Box<dyn Formatter>,
+    /// Maximum number of consecutive blank lines.
+    max: usize,
+    /// Internal, operational, number of consecutive linefeeds since the last content.
+    linefeeds: usize,
+}
+
+impl MaxBlankLines {
+    /// Creates a `MaxBlankLines`, which wraps `inner` and allows at most `max` consecutive blank
+    /// lines.
+    pub fn wrap(inner: Box<dyn Formatter>, max: usize) -> MaxBlankLines {
+        MaxBlankLines {
+            inner,
+            max,
+            linefeeds: 0,
+        }
+    }
+}
+
+impl Formatter for MaxBlankLines {
+    fn new() -> MaxBlankLines {
+        MaxBlankLines::wrap(Box::new(AlwaysIndentAlwaysLf::new()), 1)
+    }
+
+    fn set_indent_step_size(&mut self, step_size: usize) {
+        self.inner.set_indent_step_size(step_size);
+    }
+
+    fn get_indent_step_size(&self) -> usize {
+        self.inner.get_indent_step_size()
+    }
+
+    fn set_attr_wrap_width(&mut self, width: Option<usize>) {
+        self.inner.set_attr_wrap_width(width);
+    }
+
+    fn get_attr_wrap_width(&self) -> Option<usize> {
+        self.inner.get_attr_wrap_width()
+    }
+
+    fn is_noop(&self) -> bool {
+        self.inner.is_noop()
+    }
+
+    fn reset_to_defaults(&mut self) {
+        self.inner.reset_to_defaults();
+        self.linefeeds = 0;
+    }
+
+    fn reset_state(&mut self) {
+        self.inner.reset_state();
+        self.linefeeds = 0;
+    }
+
+    fn sync_state(&mut self, state: &SequenceState) {
+        self.inner.sync_state(state);
+    }
+
+    fn check(&mut self, state: &SequenceState) -> FormatChanges {
+        let mut changes = self.inner.check(state);
+        // A manual linefeed follows this check in any case.
+        let manual = usize::from(state.next.0 == Sequence::LineFeed);
+        let linefeeds = self.linefeeds + manual;
+        // Each linefeed after the first one creates a blank line.
+        if changes.new_line && linefeeds > self.max {
+            changes.new_line = false;
+        }
+        self.linefeeds = match state.next.0 {
+            Sequence::LineFeed => linefeeds + usize::from(changes.new_line),
+            _ => 0,
+        };
+        changes
+    }
+
+    fn on_document_start(&mut self) -> Option<String> {
+        self.inner.on_document_start()
+    }
+
+    fn on_document_end(&mut self) -> Option<String> {
+        self.inner.on_document_end()
+    }
+
+    fn get_ext_auto_indenting(&mut self) -> Option<&mut dyn ExtAutoIndenting> {
+        self.inner.get_ext_auto_indenting()
+    }
+}
+
+/// Internal helper, which converts tags into owned `String`s.
+fn to_strings<S: AsRef<str>>(tags: &[S]) -> impl Iterator<Item = String> + '_ {
+    tags.iter().map(|t| t.as_ref().to_string())
 }
 
 /// Stackable instruction for a Formatter implementation when closing a block.
@@ -389,8 +1145,9 @@ enum BlockClosingOp {
     Nothing,
     /// Formatter will apply line feeds arround certain tags.
     Linefeed,
-    /// Formatter will insert line feeds and decrease current indenting.
-    LfIndentLess,
+    /// Formatter will insert line feeds and decrease current indenting by the given step size,
+    /// which has been used when increasing it.
+    LfIndentLess(usize),
 }
 
 #[cfg(test)]
@@ -428,6 +1185,13 @@ mod tests {
             Box::new(NoFormatting::new()),
             Box::new(AlwaysIndentAlwaysLf::new()),
             Box::new(AutoIndent::new()),
+            Box::new(HtmlBlockIndent::new()),
+            Box::new(BlockInline::new()),
+            Box::new(DiffFriendly::new()),
+            Box::new(AlignClosing::new()),
+            Box::new(Canonical::new()),
+            Box::new(FnFormatter::new()),
+            Box::new(MaxBlankLines::new()),
         ]
     }
 
@@ -441,9 +1205,12 @@ mod tests {
     #[test]
     fn after_reset_default_again() {
         for fmt in get_formatters_list().iter_mut() {
+            let attr_wrap_width = fmt.get_attr_wrap_width();
             fmt.set_indent_step_size(DEFAULT_INDENT + 1);
+            fmt.set_attr_wrap_width(Some(80));
             fmt.reset_to_defaults();
             assert_eq!(fmt.get_indent_step_size(), DEFAULT_INDENT);
+            assert_eq!(fmt.get_attr_wrap_width(), attr_wrap_width);
         }
     }
 
@@ -626,6 +1393,36 @@ mod tests {
         // already tested that before two times.
     }
 
+    #[test]
+    fn html_block_indent_tag_kinds() {
+        let mut fmtr = HtmlBlockIndent::new();
+        assert_eq!(fmtr.tag_kind("section"), HtmlTagKind::Block);
+        assert_eq!(fmtr.tag_kind("p"), HtmlTagKind::Line);
+        assert_eq!(fmtr.tag_kind("span"), HtmlTagKind::Inline);
+
+        fmtr.set_tag_kind(&["section", "span"], HtmlTagKind::Line);
+        fmtr.set_tag_kind(&["p"], HtmlTagKind::Inline);
+        assert_eq!(fmtr.tag_kind("section"), HtmlTagKind::Line);
+        assert_eq!(fmtr.tag_kind("span"), HtmlTagKind::Line);
+        assert_eq!(fmtr.tag_kind("p"), HtmlTagKind::Inline);
+
+        // <section><span>Text</span>Text</section>
+        assert_eq!(
+            fmtr.check(&SequenceState::open_open("section", "span")),
+            NOTHING
+        );
+        assert_eq!(fmtr.check(&SequenceState::open_text("span")), NOTHING);
+        assert_eq!(fmtr.check(&SequenceState::text_close("span")), NOTHING);
+        assert_eq!(fmtr.check(&SequenceState::close_text("span")), LINEFEED);
+        assert_eq!(fmtr.check(&SequenceState::text_close("section")), NOTHING);
+        assert_eq!(fmtr.check(&SequenceState::close_text("section")), LINEFEED);
+
+        fmtr.reset_to_defaults();
+        assert_eq!(fmtr.tag_kind("section"), HtmlTagKind::Block);
+        assert_eq!(fmtr.tag_kind("p"), HtmlTagKind::Line);
+        assert_eq!(fmtr.tag_kind("span"), HtmlTagKind::Inline);
+    }
+
     #[test]
     fn auto_indenting_mixed_rules() {
         let mut fmtr = Box::new(AutoIndent::new());
@@ -698,4 +1495,179 @@ mod tests {
         );
         assert_eq!(fmtr.check(&SequenceState::close_text("body")), LINEFEED);
     }
+
+    #[test]
+    fn fn_formatter_delegates_to_closure() {
+        let mut fmtr = FnFormatter::from_fn(|state| {
+            FormatChanges::may_lf(matches!(state.last.0, Sequence::Closing))
+        });
+        assert_eq!(
+            fmtr.check(&SequenceState::open_open("body", "div")),
+            NOTHING
+        );
+        assert_eq!(fmtr.check(&SequenceState::text_close("div")), NOTHING);
+        assert_eq!(
+            fmtr.check(&SequenceState::close_close("div", "body")),
+            LINEFEED
+        );
+        assert_eq!(fmtr.check(&SequenceState::close_text("body")), LINEFEED);
+    }
+
+    #[test]
+    fn auto_indenting_tag_indent_step() {
+        let mut fmtr = AutoIndent::new();
+        fmtr.add_tags_to_rule(&["body", "div", "pre"], AutoFmtRule::IndentAlways)
+            .unwrap();
+        fmtr.set_tag_indent_step("pre", 2);
+
+        // <body><pre><div></div></pre></body>
+        let mut state = SequenceState::open_open("body", "pre");
+        state.indent = 0;
+        assert_eq!(fmtr.check(&state), FormatChanges::lf_indent_more(0, 4));
+        let mut state = SequenceState::open_open("pre", "div");
+        state.indent = 4;
+        assert_eq!(fmtr.check(&state), FormatChanges::lf_indent_more(4, 2));
+        let mut state = SequenceState::open_close("div", "div");
+        state.indent = 6;
+        assert_eq!(fmtr.check(&state), LINEFEED);
+        let mut state = SequenceState::close_close("div", "pre");
+        state.indent = 6;
+        assert_eq!(fmtr.check(&state), FormatChanges::lf_indent_less(6, 2));
+        let mut state = SequenceState::close_close("pre", "body");
+        state.indent = 4;
+        assert_eq!(fmtr.check(&state), FormatChanges::lf_indent_less(4, 4));
+    }
+
+    #[test]
+    fn diff_friendly_text_on_own_line() {
+        let mut fmtr = DiffFriendly::new();
+        assert_eq!(fmtr.get_attr_wrap_width(), Some(0));
+        assert_eq!(fmtr.check(&SequenceState::open_text("p")), LF_INDENT_MORE);
+        assert_eq!(fmtr.check(&SequenceState::text_open("b")), LINEFEED);
+        assert_eq!(fmtr.check(&SequenceState::text_close("p")), LF_INDENT_LESS);
+    }
+
+    #[test]
+    fn auto_indenting_ruleset() {
+        let mut fmtr = AutoIndent::new();
+        let empty: &[String] = &[];
+        assert_eq!(fmtr.ruleset(), (empty, empty, empty));
+        fmtr.add_tags_to_rule(&["body", "section"], AutoFmtRule::IndentAlways)
+            .unwrap();
+        fmtr.add_tags_to_rule(&["html"], AutoFmtRule::LfAlways)
+            .unwrap();
+        fmtr.add_tags_to_rule(&["p"], AutoFmtRule::LfClosing)
+            .unwrap();
+        let (indent_always, lf_always, lf_closing) = fmtr.ruleset();
+        assert_eq!(indent_always, &["body".to_string(), "section".to_string()]);
+        assert_eq!(lf_always, &["html".to_string()]);
+        assert_eq!(lf_closing, &["p".to_string()]);
+
+        let fmtr = AutoIndent::html();
+        assert_eq!(fmtr.ruleset().0.len(), HTML_BLOCK_TAGS.len());
+        assert!(fmtr.ruleset().1.is_empty());
+    }
+
+    #[test]
+    fn ext_auto_indenting_default_ruleset() {
+        #[derive(Debug)]
+        struct Minimal;
+
+        impl Formatter for Minimal {
+            fn new() -> Minimal {
+                Minimal
+            }
+
+            fn check(&mut self, _: &SequenceState) -> FormatChanges {
+                FormatChanges::nothing()
+            }
+        }
+
+        impl ExtAutoIndenting for Minimal {
+            fn add_tags_to_rule(&mut self, _: &[&str], _: AutoFmtRule) -> Result<()> {
+                Ok(())
+            }
+
+            fn reset_ruleset(&mut self) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let fmtr = Minimal::new();
+        let (indent_always, lf_always, lf_closing) = fmtr.ruleset();
+        assert!(indent_always.is_empty() && lf_always.is_empty() && lf_closing.is_empty());
+    }
+
+    #[test]
+    fn auto_indent_with_tag_kinds() {
+        let fmtr = AutoIndent::with_tag_kinds(&["div"], &["p"], &["b"]);
+        let (indent_always, lf_always, lf_closing) = fmtr.ruleset();
+        assert_eq!(indent_always, &["div".to_string()]);
+        assert!(lf_always.is_empty());
+        assert_eq!(lf_closing, &["div".to_string(), "p".to_string()]);
+        assert_eq!(fmtr.inline_tags, vec!["b".to_string()]);
+
+        // All HTML presets share the same ruleset.
+        let html = AutoIndent::html();
+        let block_indent = HtmlBlockIndent::new();
+        assert_eq!(block_indent.auto_indent.ruleset(), html.ruleset());
+        let block_inline = BlockInline::with_tags(HTML_BLOCK_TAGS, HTML_INLINE_TAGS);
+        assert_eq!(block_inline.auto_indent.ruleset().0, html.ruleset().0);
+        assert_eq!(block_inline.auto_indent.inline_tags, html.inline_tags);
+
+        // The XML preset matches every tag, text and empty elements stay in line.
+        let mut xml = AutoIndent::xml();
+        assert_eq!(
+            xml.check(&SequenceState::open_open("root", "item")),
+            LF_INDENT_MORE
+        );
+        assert_eq!(xml.check(&SequenceState::open_text("item")), NOTHING);
+        assert_eq!(xml.check(&SequenceState::text_close("item")), NOTHING);
+        assert_eq!(
+            xml.check(&SequenceState::open_close("empty", "empty")),
+            NOTHING
+        );
+    }
+
+    #[test]
+    fn auto_indenting_inline_tags() {
+        let mut fmtr = AutoIndent::new();
+        fmtr.add_tags_to_rule(&["div", "a"], AutoFmtRule::IndentAlways)
+            .unwrap();
+        fmtr.inline_tags.push("a".to_string());
+
+        // <div><a>text</a></div>
+        assert_eq!(
+            fmtr.check(&SequenceState::open_open("div", "a")),
+            LF_INDENT_MORE
+        );
+        assert_eq!(fmtr.check(&SequenceState::open_text("a")), NOTHING);
+        assert_eq!(fmtr.check(&SequenceState::text_close("a")), NOTHING);
+        assert_eq!(
+            fmtr.check(&SequenceState::close_close("a", "div")),
+            LF_INDENT_LESS
+        );
+    }
+
+    #[test]
+    fn max_blank_lines_clamps_runs() {
+        let always_lf = || Box::new(FnFormatter::from_fn(|_| FormatChanges::lf()));
+        let close_lf = SequenceState::teststate(TagSequence::closing("p"), TagSequence::linefeed());
+        let lf_lf = SequenceState::teststate(TagSequence::linefeed(), TagSequence::linefeed());
+
+        // </p>, 2 manual linefeeds, <br>: 3 linefeeds of the inner formatter.
+        let mut fmtr = MaxBlankLines::wrap(always_lf(), 2);
+        assert_eq!(fmtr.check(&close_lf), LINEFEED);
+        assert_eq!(fmtr.check(&lf_lf), NOTHING);
+        assert_eq!(fmtr.check(&SequenceState::lf_self_closing("br")), NOTHING);
+        // Content resets the run.
+        assert_eq!(
+            fmtr.check(&SequenceState::self_closing_close("br", "p")),
+            LINEFEED
+        );
+
+        let mut fmtr = MaxBlankLines::wrap(always_lf(), 0);
+        assert_eq!(fmtr.check(&close_lf), NOTHING);
+        assert_eq!(fmtr.check(&SequenceState::lf_self_closing("br")), NOTHING);
+    }
 }
@@ -0,0 +1,65 @@
+//! This module contains helpers of `MarkupSth` for the repetitive tags in the head of HTML
+//! documents, such as `<meta>` and stylesheet `<link>` tags.
+
+use crate::{MarkupSth, Result};
+
+impl MarkupSth<'_> {
+    /// Inserts the character encoding of the document, e.g. `<meta charset="utf-8">`.
+    pub fn meta_charset(&mut self, charset: &str) -> Result<()> {
+        self.self_closing("meta")?;
+        self.properties(&[("charset", charset)])
+    }
+
+    /// Inserts named metadata, e.g. `<meta name="viewport" content="width=device-width">`.
+    pub fn meta(&mut self, name: &str, content: &str) -> Result<()> {
+        self.self_closing("meta")?;
+        self.properties(&[("name", name), ("content", content)])
+    }
+
+    /// Inserts a link to a stylesheet, e.g. `<link rel="stylesheet" href="css/style.css">`.
+    pub fn stylesheet(&mut self, href: &str) -> Result<()> {
+        self.self_closing("link")?;
+        self.properties(&[("rel", "stylesheet"), ("href", href)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Language, MarkupSth};
+
+    #[test]
+    fn head_helpers() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.open("head").unwrap();
+        mus.meta_charset("utf-8").unwrap();
+        mus.meta("viewport", "width=device-width, initial-scale=1")
+            .unwrap();
+        mus.stylesheet("css/style.css").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n<head>",
+                r#"<meta charset="utf-8">"#,
+                r#"<meta name="viewport" content="width=device-width, initial-scale=1">"#,
+                r#"<link rel="stylesheet" href="css/style.css">"#,
+                "</head>"
+            ]
+        );
+    }
+
+    #[test]
+    fn head_helpers_in_xhtml() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::XHtml).unwrap();
+        mus.doctype_linefeed = false;
+        mus.syntax.doctype = None;
+        mus.meta_charset("utf-8").unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(document, r#"<meta charset="utf-8" />"#);
+    }
+}
@@ -0,0 +1,220 @@
+//! This module contains `LatexSth`, a writer for LaTeX documents. It reuses `MarkupSth` with a
+//! small custom syntax: Environments are tag pairs written as `\begin{name}` and `\end{name}` and
+//! commands are self-closing tags starting with `\`, so its formatter handles the indenting of
+//! environments.
+//!
+//! ### Example
+//!
+//! ```
+//! use markupsth::LatexSth;
+//!
+//! let mut document = String::new();
+//! let mut tex = LatexSth::new(&mut document).unwrap();
+//! tex.begin_env("itemize").unwrap();
+//! tex.command("item", &[]).unwrap();
+//! tex.text("Costs & benefits").unwrap();
+//! tex.end_env().unwrap();
+//! tex.finalize().unwrap();
+//! assert_eq!(
+//!     document,
+//!     "\\begin{itemize}\n    \\item Costs \\& benefits\n\\end{itemize}"
+//! );
+//! ```
+
+use crate::{
+    format::{FormatChanges, Sequence, DEFAULT_INDENT},
+    formatters::FnFormatter,
+    syntax::{
+        escape_with, Insertion::*, Language, SelfClosingTagConfig, SyntaxConfig, TagPairConfig,
+    },
+    MarkupSth, Result,
+};
+
+/// A writer for documents in LaTeX, based on `MarkupSth`.
+#[derive(Debug)]
+pub struct LatexSth<'d> {
+    /// The underlying writer.
+    markup: MarkupSth<'d>,
+    /// Stack of open environments.
+    envs: Vec<String>,
+    /// Whether the last inserted command has no arguments, so following text has to be separated.
+    bare_command: bool,
+}
+
+impl<'d> LatexSth<'d> {
+    /// Creates a new LatexSth instance, which writes into `document`.
+    pub fn new(document: &'d mut String) -> Result<LatexSth<'d>> {
+        let mut markup = MarkupSth::new(document, Language::Other(latex_syntax()))?;
+        markup.set_formatter(Box::new(FnFormatter::from_fn(|state| {
+            match (&state.last.0, &state.next.0) {
+                (Sequence::Initial, _) | (Sequence::Text, Sequence::Text) => {
+                    FormatChanges::nothing()
+                }
+                // Text follows a command without arguments, e.g. `\item`, on the same line.
+                (Sequence::SelfClosing, Sequence::Text) if !state.last.1.contains('{') => {
+                    FormatChanges::nothing()
+                }
+                (Sequence::Opening, Sequence::Closing) => FormatChanges::lf(),
+                (Sequence::Opening, _) => {
+                    FormatChanges::lf_indent_more(state.indent, DEFAULT_INDENT)
+                }
+                (_, Sequence::Closing) => {
+                    FormatChanges::lf_indent_less(state.indent, DEFAULT_INDENT)
+                }
+                _ => FormatChanges::lf(),
+            }
+        })));
+        Ok(LatexSth {
+            markup,
+            envs: Vec::new(),
+            bare_command: false,
+        })
+    }
+
+    /// Begins a new environment, e.g. `\begin{itemize}`. Environments can be nested.
+    pub fn begin_env(&mut self, name: &str) -> Result<()> {
+        check_name(name)?;
+        self.markup.open(name)?;
+        self.envs.push(name.to_string());
+        self.bare_command = false;
+        Ok(())
+    }
+
+    /// Ends the innermost open environment.
+    pub fn end_env(&mut self) -> Result<()> {
+        if self.envs.pop().is_none() {
+            return Err("LatexSth: no environment is open".into());
+        }
+        self.markup.close()?;
+        self.bare_command = false;
+        Ok(())
+    }
+
+    /// Inserts a command with its arguments, e.g. `\section{Title}`. Commands start on their own
+    /// line. The arguments will be escaped like text.
+    pub fn command(&mut self, name: &str, args: &[&str]) -> Result<()> {
+        check_name(name)?;
+        let mut command = name.to_string();
+        for arg in args.iter() {
+            command.push('{');
+            command.push_str(&escape_with(arg, &latex_escapes()));
+            command.push('}');
+        }
+        self.markup.self_closing(&command)?;
+        self.bare_command = args.is_empty();
+        Ok(())
+    }
+
+    /// Inserts text, in which the special characters of LaTeX will be escaped. Text following a
+    /// command without arguments, e.g. `\item`, gets separated by a space.
+    pub fn text(&mut self, text: &str) -> Result<()> {
+        if self.bare_command && !text.starts_with(char::is_whitespace) {
+            self.markup.text(" ")?;
+        }
+        self.markup.text(text)?;
+        self.bare_command = false;
+        Ok(())
+    }
+
+    /// Finalizes the document. Returns an error, if an environment is still open.
+    pub fn finalize(self) -> Result<()> {
+        if let Some(env) = self.envs.last() {
+            return Err(format!("LatexSth: environment '{}' has not been ended", env).into());
+        }
+        self.markup.finalize()
+    }
+}
+
+/// Internal check method, whether `name` is a valid name of a command or an environment, which
+/// consists of letters only (an environment may end with `*`).
+fn check_name(name: &str) -> Result<()> {
+    let letters = name.strip_suffix('*').unwrap_or(name);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!("LatexSth: invalid name '{}'", name).into());
+    }
+    Ok(())
+}
+
+/// Internal escape table for the special characters of LaTeX.
+fn latex_escapes() -> Vec<(char, String)> {
+    vec![
+        ('\\', "\\textbackslash{}".to_string()),
+        ('#', "\\#".to_string()),
+        ('$', "\\$".to_string()),
+        ('%', "\\%".to_string()),
+        ('&', "\\&".to_string()),
+        ('_', "\\_".to_string()),
+        ('{', "\\{".to_string()),
+        ('}', "\\}".to_string()),
+    ]
+}
+
+/// Internal syntax of LaTeX: Environments are tag pairs delimited by `\begin{`/`\end{` and `}`,
+/// commands are self-closing tags starting with `\`.
+fn latex_syntax() -> SyntaxConfig {
+    SyntaxConfig {
+        doctype: None,
+        self_closing: Some(SelfClosingTagConfig {
+            before: Single('\\'),
+            after: Nothing,
+        }),
+        tag_pairs: Some(TagPairConfig {
+            opening_before: Str("\\begin{"),
+            opening_after: Single('}'),
+            closing_before: Str("\\end{"),
+            closing_after: Single('}'),
+        }),
+        properties: None,
+        text_escapes: latex_escapes(),
+        attr_escapes: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testfile;
+
+    #[test]
+    fn document_with_nested_environments() {
+        let mut document = String::new();
+        let mut tex = LatexSth::new(&mut document).unwrap();
+        tex.command("documentclass", &["article"]).unwrap();
+        tex.begin_env("document").unwrap();
+        tex.command("section", &["Results & Notes"]).unwrap();
+        tex.text("Costs rose by 5% in Q_1, see {table} #3 for $.")
+            .unwrap();
+        tex.begin_env("itemize").unwrap();
+        tex.command("item", &[]).unwrap();
+        tex.text("First").unwrap();
+        tex.command("item", &[]).unwrap();
+        tex.text("Path C:\\temp").unwrap();
+        tex.begin_env("enumerate").unwrap();
+        tex.command("item", &[]).unwrap();
+        tex.text("Nested").unwrap();
+        tex.end_env().unwrap();
+        tex.end_env().unwrap();
+        tex.begin_env("equation*").unwrap();
+        tex.end_env().unwrap();
+        tex.end_env().unwrap();
+        tex.finalize().unwrap();
+
+        assert_eq!(document, testfile("latex_nested_envs.tex"));
+    }
+
+    #[test]
+    fn invalid_names_and_open_environments() {
+        let mut document = String::new();
+        let mut tex = LatexSth::new(&mut document).unwrap();
+        assert!(tex.end_env().is_err());
+        assert!(tex.begin_env("").is_err());
+        assert!(tex.begin_env("item}").is_err());
+        assert!(tex.command("a b", &[]).is_err());
+        tex.begin_env("center").unwrap();
+        let err = tex.finalize().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "LatexSth: environment 'center' has not been ended"
+        );
+    }
+}
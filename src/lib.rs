@@ -17,6 +17,17 @@
 //! formatting styles, or any other kind of meaningful modifications. Feel free to contact me via
 //! Email provided in the manifest file.
 //!
+//! ### Escaping of text content
+//!
+//! Unlike version 0.2, `MarkupSth::text()` escapes text content of HTML and XML by default,
+//! e.g. `&` becomes `&amp;`, except within `script` and `style`. This is a breaking change: Text,
+//! which has already been escaped by the caller, gets escaped twice, e.g. `&amp;amp;`. Insert such
+//! text by `MarkupSth::text_unchecked()` or disable `MarkupSth::escape_text`.
+//!
+//! Likewise, property values get `&`, `<` and their quote character escaped by
+//! `SyntaxConfig::attr_escapes` and `MarkupSth::attr_quote_preference`. Clear `attr_escapes` to
+//! pass already escaped values.
+//!
 //! ## Examples
 //!
 //! By using an implemented Markup Language such as HTML or XML, and a pre-defined `Formatter`, you
@@ -135,16 +146,33 @@
 //! mus.finalize().unwrap();
 //! ```
 
+#[cfg(feature = "tokio")]
+pub mod asynchronous;
+pub mod attributes;
+mod document;
+pub mod dot;
 pub mod format;
 pub mod formatters;
+mod head;
+pub mod latex;
 pub mod markupsth;
+pub mod node;
+pub mod render;
 pub mod syntax;
+mod table;
 
+#[cfg(feature = "tokio")]
+pub use crate::asynchronous::AsyncMarkupSth;
 pub use crate::{
+    attributes::Attributes,
+    dot::DotSth,
     format::{AutoFmtRule, ExtAutoIndenting, Formatter},
     formatters::*,
-    markupsth::MarkupSth,
-    syntax::Language,
+    latex::LatexSth,
+    markupsth::{MarkupError, MarkupStats, MarkupSth, SanitizeMode, TrimPolicy},
+    node::Node,
+    render::Markup,
+    syntax::{HtmlTag, Language},
 };
 
 /// Crate common definition for an optional `Result` type.
@@ -172,7 +200,7 @@ mod tests {
         mus.close().unwrap();
         mus.finalize().unwrap();
 
-        assert_eq!(document, "<!DOCTYPE html><html>This is HTML</html>");
+        assert_eq!(document, "<!DOCTYPE html>\n<html>This is HTML</html>");
     }
 
     #[test]
@@ -196,7 +224,8 @@ mod tests {
         assert_eq!(
             document,
             concat![
-                r#"<!DOCTYPE html><body><section class="class">"#,
+                "<!DOCTYPE html>\n",
+                r#"<body><section class="class">"#,
                 r#"<div keya="value1" keyb="value2">"#,
                 r#"Text<img src="img.jpg"></div></section></body>"#
             ]
@@ -225,6 +254,33 @@ mod tests {
         assert_eq!(document, testfile("formatted_html_always_indent.html"),);
     }
 
+    #[test]
+    fn formatted_html_align_closing() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+
+        mus.set_formatter(Box::new(AlignClosing::new()));
+
+        mus.open("body").unwrap();
+        mus.open("section").unwrap();
+        mus.open("h1").unwrap();
+        mus.text("Title").unwrap();
+        mus.close().unwrap();
+        mus.open("div").unwrap();
+        mus.open("p").unwrap();
+        mus.text("Some ").unwrap();
+        mus.open_close_w("b", "bold").unwrap();
+        mus.text(" text").unwrap();
+        mus.close().unwrap();
+        mus.self_closing("img").unwrap();
+        properties!(mus, "src", "image.jpg").unwrap();
+        mus.open("div").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(document, testfile("formatted_html_align_closing.html"));
+    }
+
     #[test]
     fn formatted_html_auto_indent() {
         let mut document = String::new();
@@ -294,4 +350,148 @@ mod tests {
 
         assert_eq!(document, testfile("formatted_xml_auto_indent.xml"));
     }
+
+    #[test]
+    fn formatted_html_block_indent() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(HtmlBlockIndent::new()));
+
+        mus.open("html").unwrap();
+        mus.open("head").unwrap();
+        mus.open_close_w("title", "Block Indent").unwrap();
+        mus.self_closing("meta").unwrap();
+        properties!(mus, "charset", "utf-8").unwrap();
+        mus.self_closing("link").unwrap();
+        properties!(mus, "href", "css/style.css", "rel", "stylesheet").unwrap();
+        mus.close().unwrap();
+        mus.open("body").unwrap();
+        mus.open("section").unwrap();
+        mus.open_close_w("h1", "Heading").unwrap();
+        mus.open("p").unwrap();
+        mus.text("Some ").unwrap();
+        mus.open_close_w("strong", "bold").unwrap();
+        mus.text(" text with a ").unwrap();
+        mus.open("a").unwrap();
+        properties!(mus, "href", "index.html").unwrap();
+        mus.text("link").unwrap();
+        mus.close().unwrap();
+        mus.text(".").unwrap();
+        mus.close().unwrap();
+        mus.open("ul").unwrap();
+        mus.open_close_w("li", "One").unwrap();
+        mus.open_close_w("li", "Two").unwrap();
+        mus.close().unwrap();
+        mus.open("div").unwrap();
+        mus.self_closing("img").unwrap();
+        properties!(mus, "src", "image.jpg").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(document, testfile("formatted_html_block_indent.html"));
+    }
+
+    #[test]
+    fn formatted_html_block_inline() {
+        let generate = |formatter: Box<dyn Formatter>| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+            mus.set_formatter(formatter);
+            mus.open("body").unwrap();
+            mus.open("div").unwrap();
+            mus.open_close_w("h1", "Heading").unwrap();
+            mus.open("p").unwrap();
+            mus.text("Some ").unwrap();
+            mus.open_close_w("b", "bold").unwrap();
+            mus.text(" text with a ").unwrap();
+            mus.open_close_w("a", "link").unwrap();
+            mus.close().unwrap();
+            mus.open("div").unwrap();
+            mus.self_closing("img").unwrap();
+            mus.close_all().unwrap();
+            mus.finalize().unwrap();
+            document
+        };
+
+        let block = ["body", "div", "h1", "p"];
+        let inline = ["b", "a"];
+        let mut auto_indent = AutoIndent::new();
+        auto_indent
+            .add_tags_to_rule(&block, AutoFmtRule::IndentAlways)
+            .unwrap();
+        auto_indent
+            .add_tags_to_rule(&block, AutoFmtRule::LfClosing)
+            .unwrap();
+        auto_indent.inline_tags = inline.iter().map(|t| t.to_string()).collect();
+
+        let document = generate(Box::new(BlockInline::with_tags(&block, &inline)));
+        assert_eq!(document, generate(Box::new(auto_indent)));
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                "<body>\n",
+                "    <div>\n",
+                "        <h1>\n",
+                "            Heading\n",
+                "        </h1>\n",
+                "        <p>\n",
+                "            Some <b>bold</b> text with a <a>link</a>\n",
+                "        </p>\n",
+                "        <div>\n",
+                "            <img>\n",
+                "        </div>\n",
+                "    </div>\n",
+                "</body>",
+            ]
+        );
+    }
+
+    #[test]
+    fn formatted_html_attr_wrap() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(HtmlBlockIndent::new()));
+        mus.formatter.set_attr_wrap_width(Some(60));
+
+        mus.open("body").unwrap();
+        mus.open("div").unwrap();
+        properties!(mus, "id", "short").unwrap();
+        mus.self_closing("input").unwrap();
+        properties!(
+            mus,
+            "type",
+            "text",
+            "name",
+            "username",
+            "placeholder",
+            "Your name"
+        )
+        .unwrap();
+        properties!(mus, "required", "required", "autocomplete", "off").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(document, testfile("formatted_html_attr_wrap.html"));
+    }
+
+    #[test]
+    fn formatted_xml_diff_friendly() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.set_formatter(Box::new(DiffFriendly::new()));
+
+        mus.open("project").unwrap();
+        properties!(mus, "name", "markupsth", "version", "1.0").unwrap();
+        mus.self_closing("dependency").unwrap();
+        properties!(mus, "name", "totems", "version", "0.2", "scope", "test").unwrap();
+        mus.open("description").unwrap();
+        mus.text("Generated").unwrap();
+        mus.open("b").unwrap();
+        mus.text("markup").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(document, testfile("formatted_xml_diff_friendly.xml"));
+    }
 }
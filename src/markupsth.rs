@@ -2,14 +2,126 @@
 //! syntax configuration and a `Formatter`, which can be configured individually.
 
 use crate::{
-    format::{FormatChanges, Formatter, Sequence, SequenceState, TagSequence},
-    syntax::{Language, SyntaxConfig},
+    attributes::Attributes,
+    document::{Document, LineHook},
+    format::{display_width, FormatChanges, Formatter, Sequence, SequenceState, TagSequence},
+    formatters::{default_formatter, AlwaysIndentAlwaysLf, AutoIndent, NoFormatting},
+    node::Node,
+    render::Markup,
+    syntax::{
+        escape_non_ascii, escape_with, is_valid_entity_name, typography, AttrQuotes, AttrSeparator,
+        HtmlTag, Insertion, Language, NameRule, SyntaxConfig, XmlDeclaration, HTML_VOID_ELEMENTS,
+    },
 };
-use std::fmt::Write;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::{self, Write},
+    fs,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Internal counter, which makes names of temporary files unique within the process, see
+/// `MarkupSth::finalize_to_file()`.
+static TMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 /// Internal `Result` definition to make it more easy to write our default return type.
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+/// Errors of `MarkupSth`, which may be worth to be distinguished by the user. These errors will be
+/// returned boxed as all other errors, use `downcast_ref::<MarkupError>()` to inspect them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MarkupError {
+    /// A property name contains characters, which are not allowed in property names.
+    InvalidAttributeName(String),
+    /// A tag name is not valid by the `NameRule` of `MarkupSth`.
+    InvalidTagName(String),
+    /// An entity name is not valid, see `syntax::is_valid_entity_name`.
+    InvalidEntityName(String),
+    /// A tag is not in `MarkupSth::allowed_tags`.
+    DisallowedTag(String),
+    /// Tags are still open on finalization, see `MarkupSth::strict_close`.
+    UnclosedTags { remaining: Vec<String> },
+    /// Opening a tag would exceed the maximum depth, see `MarkupSth::set_max_depth()`.
+    MaxDepthExceeded(usize),
+    /// A second root element has been inserted, see `MarkupSth::single_root`.
+    MultipleRoots(String),
+    /// A self-closing tag is not a void element, see `MarkupSth::strict_void_elements`.
+    NotVoidElement(String),
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarkupError::InvalidAttributeName(name) => {
+                write!(f, "MarkupSth: invalid property name '{}'", name)
+            }
+            MarkupError::InvalidTagName(name) => {
+                write!(f, "MarkupSth: invalid tag name '{}'", name)
+            }
+            MarkupError::InvalidEntityName(name) => {
+                write!(f, "MarkupSth: invalid entity name '{}'", name)
+            }
+            MarkupError::DisallowedTag(tag) => {
+                write!(f, "MarkupSth: tag '{}' is not allowed", tag)
+            }
+            MarkupError::UnclosedTags { remaining } => {
+                write!(f, "MarkupSth: unclosed tags {}", remaining.join(", "))
+            }
+            MarkupError::MaxDepthExceeded(max) => {
+                write!(f, "MarkupSth: maximum depth of {} tags exceeded", max)
+            }
+            MarkupError::MultipleRoots(tag) => {
+                write!(f, "MarkupSth: tag '{}' would be a second root element", tag)
+            }
+            MarkupError::NotVoidElement(tag) => {
+                write!(
+                    f,
+                    "MarkupSth: tag '{}' is no void element to be self-closing",
+                    tag
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MarkupError {}
+
+/// Selector for the handling of tags, which are not allowed, see `MarkupSth::allowed_tags`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SanitizeMode {
+    /// Disallowed tags return `MarkupError::DisallowedTag`.
+    Error,
+    /// Disallowed tags and their properties are silently skipped, their content is kept.
+    Skip,
+}
+
+/// Selector for trimming whitespaces of text content in `text()`, see `MarkupSth::trim_policy`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TrimPolicy {
+    /// Text is inserted as it is.
+    None,
+    /// Leading and trailing whitespaces are removed.
+    Trim,
+    /// Leading whitespaces are removed.
+    TrimStart,
+    /// Trailing whitespaces are removed.
+    TrimEnd,
+}
+
+impl TrimPolicy {
+    /// Applies this policy to `text`.
+    pub fn apply<'t>(&self, text: &'t str) -> &'t str {
+        match self {
+            TrimPolicy::None => text,
+            TrimPolicy::Trim => text.trim(),
+            TrimPolicy::TrimStart => text.trim_start(),
+            TrimPolicy::TrimEnd => text.trim_end(),
+        }
+    }
+}
+
 /// The core and 'writer' of this crate. Configure and use one instance of `MarkupSth` to generate
 /// your Markup-Language content. Configurable sub-items are about syntax of used Markup Language
 /// and about formatting. This crate provides some pre-defined configurations, which can be used
@@ -52,23 +164,171 @@ pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 pub struct MarkupSth<'d> {
     /// Syntax configuration of `MarkupSth`.
     pub syntax: SyntaxConfig,
-    /// Formatting configuration of `MarkupSth`.
+    /// Formatting configuration of `MarkupSth`. Default depends on the `Language`, see
+    /// `formatters::default_formatter()`.
     pub formatter: Box<dyn Formatter>,
+    /// Optional maximum width of a line in columns for wrapping text content. Text inserted by
+    /// `text()` will be wrapped between words, so that a line does not exceed this display width
+    /// (see `format::display_width`). Default is `None`, no wrapping.
+    pub text_wrap_width: Option<usize>,
+    /// Strict mode for properties: When set, `properties()` returns an error if the same property
+    /// name appears twice within one tag. Default is `false`, properties will not be deduplicated.
+    pub assert_no_duplicate_attrs: bool,
+    /// When set, a tag pair without any content in between (opening tag immediately followed by
+    /// its closing tag) will be written as self-closing tag element, e.g. `<div/>` instead of
+    /// `<div></div>`. This is only possible, if the syntax provides self-closing tag elements with
+    /// the same insertion before the tag as opening tags. If a self-closing tag does not close the
+    /// element in this syntax, e.g. `<span>` in HTML, only `void_elements` will be collapsed.
+    /// Default is `false`.
+    pub collapse_empty_pairs: bool,
+    /// When set, only these tags can be inserted, others are handled by `sanitize_mode`. Default is
+    /// `None`.
+    pub allowed_tags: Option<HashSet<String>>,
+    /// Handling of tags, which are not in `allowed_tags`. Default is `SanitizeMode::Error`.
+    pub sanitize_mode: SanitizeMode,
+    /// When set, self-closing tags are closed by ` />` (XHTML style, e.g. `<br />`) instead of the
+    /// configured insertion of the syntax. Default is `false`.
+    pub self_closing_slash: bool,
+    /// Optional tag, which wraps blocks inserted by `verbatim_block()`, e.g. `pre`. Default is
+    /// `None`.
+    pub verbatim_tag: Option<String>,
+    /// When set, finalizing the document returns `MarkupError::UnclosedTags`, if there are still
+    /// open tags. Default is `false`.
+    pub strict_close: bool,
+    /// Tags, which are void elements. If not empty, `collapse_empty_pairs` will only collapse these
+    /// tags. Default is `syntax::HTML_VOID_ELEMENTS` for HTML, HTML5 and XHTML.
+    pub void_elements: Vec<String>,
+    /// When set, `self_closing()` returns `MarkupError::NotVoidElement` for tags, which are not in
+    /// `void_elements`, e.g. `<div>`. Default is `true` for HTML5 and XHTML.
+    pub strict_void_elements: bool,
+    /// When set, the properties of each call of `properties()` will be sorted by name, e.g. for
+    /// canonical output (see `Canonical`). Properties of separate calls are not sorted among each
+    /// other. Default is `false`.
+    pub sort_attributes: bool,
+    /// When set, names of tags and properties will be validated. Tag names will be validated by
+    /// `name_rule`, names of properties will be rejected, if they contain whitespaces or any of the
+    /// characters `<`, `>`, `/`, `=`, `"` and `'`. Default is `false`.
+    pub validate_names: bool,
+    /// Rule for validating tag names, see `validate_names`. Default depends on the `Language`.
+    pub name_rule: NameRule,
+    /// Selection of quotes around property values. `AttrQuotes::Prefer('"')` chooses the quotes
+    /// per value to avoid escaping. Default is `AttrQuotes::Config`, the quotes of the syntax.
+    pub attr_quote_preference: AttrQuotes,
+    /// When set, text content will be escaped by `text()` (see `SyntaxConfig::text_escapes`),
+    /// except within tags listed in `raw_text_tags`. Default is `true`, if the syntax has any text
+    /// escapes, as HTML and XML.
+    pub escape_text: bool,
+    /// Tags, whose text content will not be escaped, e.g. `script` and `style` in HTML. Such text
+    /// must not contain the closing tag of its tag. Default is `script` and `style` for HTML.
+    pub raw_text_tags: Vec<String>,
+    /// When set, `text()` substitutes straight double quotes and `--` by typographic entities (see
+    /// `syntax::typography`), except within tags listed in `raw_text_tags`. Default is `false`.
+    pub typography: bool,
+    /// Trimming of whitespaces of text content in `text()`, e.g. for template data with stray
+    /// whitespaces. Default is `TrimPolicy::None`.
+    pub trim_policy: TrimPolicy,
+    /// When set, all non-ASCII characters in text content and property values will be replaced by
+    /// numeric character references, e.g. `&#xE9;` (see `syntax::escape_non_ascii`), except within
+    /// tags listed in `raw_text_tags`. Default is `false`.
+    pub ascii_only: bool,
+    /// Optional threshold for XML documents: If the text content of `text()` contains more
+    /// characters, which would have to be escaped, it will be wrapped into a CDATA section instead.
+    /// `typography` does not apply to CDATA sections and `ascii_only` disables them. Default is
+    /// `None`.
+    pub auto_cdata_threshold: Option<usize>,
+    /// Optional maximum number of decimals of floats formatted by helpers like `attr_float()` and
+    /// `attr_points()`. Floats will be rounded and trailing zeros removed, e.g. `0.3` instead of
+    /// `0.30000000000000004`. Default is `None`, the shortest representation of each float.
+    pub float_precision: Option<usize>,
+    /// When set, the byte order mark (U+FEFF) will be written at the very beginning of the
+    /// document, before the doctype, e.g. for XML consumers requiring it. In files it is the byte
+    /// sequence `EF BB BF`. Default is `false`.
+    pub write_bom: bool,
+    /// When set, tags receiving both text and tags as direct children (mixed content) will be
+    /// recorded, see `mixed_content()`. Whitespace-only text is ignored. Default is `false`.
+    pub warn_mixed_content: bool,
+    /// When set, inserting a tag after the root element has been closed returns
+    /// `MarkupError::MultipleRoots`. Default is `true` for XML, but `false` for documents continued
+    /// by `append_to()`.
+    pub single_root: bool,
+    /// Base indenting in columns, which is inserted at the beginning of the document and added to
+    /// the indenting of every following line, e.g. to embed the generated document into an already
+    /// indented host document. Default is `0`.
+    pub base_indent: usize,
+    /// When set, the first sequence after the doctype will be placed on a new line, independent of
+    /// the formatter. Default is `true` for HTML and XML.
+    pub doctype_linefeed: bool,
     /// Sequence state stored interally.
     seq_state: SequenceState,
+    /// Whether a root element has been completed, see `single_root`.
+    root_done: bool,
+    /// Whether the last opening tag has been inserted by `self_closing_or_pair()` and its kind is
+    /// not decided yet.
+    deferred_pair: bool,
+    /// Optional maximum depth of nested tag pairs, see `set_max_depth()`.
+    max_depth: Option<usize>,
+    /// Whether each open tag has received text and tags as direct children, see
+    /// `warn_mixed_content`.
+    content_stack: Vec<(bool, bool)>,
+    /// Tags, which have been detected with mixed content.
+    mixed_content: Vec<String>,
+    /// Whether the language supports CDATA sections, see `auto_cdata_threshold`.
+    cdata: bool,
+    /// Name of the last inserted tag, for naming it in error messages.
+    last_tag: String,
+    /// Names of the properties, which have been added to the current tag.
+    attr_names: Vec<String>,
+    /// Whether the properties of the current tag are wrapped, one per line.
+    attrs_wrapped: bool,
+    /// Column to align wrapped properties of the current tag to.
+    attr_column: usize,
+    /// Depths of the tag stack, at which opening tags have been skipped (see `allowed_tags`).
+    skipped_tags: Vec<usize>,
+    /// Whether the last tag has been skipped, so its properties will be skipped too.
+    skip_properties: bool,
+    /// Whether the beginning of the document (incl. the doctype) has already been written.
+    document_started: bool,
+    /// Whether an already started document is continued, see `append_to()`.
+    appended: bool,
+    /// Depth of the outermost open tag pair with `xml:space="preserve"`, inside which the
+    /// `Formatter` is not asked for any changes.
+    preserve_depth: Option<usize>,
+    /// Formatters replaced by `with_formatter_scope()`, which still get to know every sequence,
+    /// so their state stays consistent, when they get restored. Each with the indenting it would
+    /// have applied.
+    outer_formatters: Vec<(Box<dyn Formatter>, usize)>,
+    /// Counters of written elements.
+    stats: MarkupStats,
     /// Simple optimization.
     indent_str: String,
     /// Reference to a Document.
-    document: &'d mut String,
+    document: Document<'d>,
+}
+
+/// Statistics about the written elements of a `MarkupSth`, see `MarkupSth::stats()`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MarkupStats {
+    /// Number of opened tag pairs.
+    pub opened: usize,
+    /// Number of self-closing tags.
+    pub self_closed: usize,
+    /// Number of inserted text contents.
+    pub text_nodes: usize,
+    /// Maximum depth of nested tag pairs.
+    pub max_depth: usize,
 }
 
 /// Do not repeat yourself!
 macro_rules! final_op_arm {
     (selfclosing $self:expr) => {{
-        $self.document.write_fmt(format_args!(
-            "{}",
-            $self.syntax.self_closing.as_ref().unwrap().after
-        ))?;
+        if $self.self_closing_slash {
+            $self.document.write_str(" />")?;
+        } else {
+            $self.document.write_fmt(format_args!(
+                "{}",
+                $self.syntax.self_closing.as_ref().unwrap().after
+            ))?;
+        }
     }};
     (opening $self:expr) => {{
         $self.document.write_fmt(format_args!(
@@ -84,31 +344,265 @@ macro_rules! final_op_arm {
     }};
 }
 
-pub(crate) use final_op_arm;
-
 impl<'d> MarkupSth<'d> {
     /// New type pattern for creating a new MarkupSth instance.
     pub fn new(document: &'d mut String, ml: Language) -> Result<MarkupSth<'d>> {
+        MarkupSth::with_document(Document::from_string(document), ml)
+    }
+
+    /// Creates a new MarkupSth instance with a formatter for readable output. For HTML this is
+    /// `AutoIndent::html()`, for XML and other languages, whose tags are unknown, this is
+    /// `AlwaysIndentAlwaysLf`.
+    pub fn pretty(document: &'d mut String, ml: Language) -> Result<MarkupSth<'d>> {
+        let formatter: Box<dyn Formatter> = match ml {
+            Language::Html | Language::Html5 | Language::XHtml => Box::new(AutoIndent::html()),
+            _ => Box::new(AlwaysIndentAlwaysLf::new()),
+        };
+        let mut mus = MarkupSth::new(document, ml)?;
+        mus.formatter = formatter;
+        Ok(mus)
+    }
+
+    /// Creates a new MarkupSth instance without any formatting (see `NoFormatting`), for compact
+    /// output.
+    pub fn compact(document: &'d mut String, ml: Language) -> Result<MarkupSth<'d>> {
+        let mut mus = MarkupSth::new(document, ml)?;
+        mus.formatter = Box::new(NoFormatting::new());
+        Ok(mus)
+    }
+
+    /// Creates a new MarkupSth instance, which writes into any implementor of `std::fmt::Write`
+    /// instead of a `String`.
+    pub fn from_writer(writer: &'d mut dyn Write, ml: Language) -> Result<MarkupSth<'d>> {
+        MarkupSth::with_document(Document::from_writer(writer), ml)
+    }
+
+    /// Creates a new MarkupSth instance, which writes into a new file at `path`. An existing file
+    /// will be truncated. The file is written buffered and flushed when the document gets
+    /// finalized. If writing has failed, `finalize()` returns the `std::io::Error`.
+    pub fn create_file(path: impl AsRef<Path>, ml: Language) -> Result<MarkupSth<'d>> {
+        MarkupSth::with_document(Document::new_file(path)?, ml)
+    }
+
+    /// Creates a new MarkupSth instance, which continues an already started document. Neither the
+    /// doctype nor the beginning of the `Formatter` will be inserted, the existing content is
+    /// treated like preceding text.
+    pub fn append_to(document: &'d mut String, ml: Language) -> Result<MarkupSth<'d>> {
+        let mut mus = MarkupSth::with_document(Document::from_string(document), ml)?;
+        mus.seq_state.last = TagSequence::text();
+        mus.single_root = false;
+        mus.appended = true;
+        Ok(mus)
+    }
+
+    /// Creates a new MarkupSth instance, which writes into an owned buffer. The buffer gets
+    /// drained by `take_buffer()`, see `AsyncMarkupSth`.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn buffered(ml: Language) -> Result<MarkupSth<'static>> {
+        MarkupSth::with_document(Document::buffered(), ml)
+    }
+
+    /// Internal constructor for any kind of `Document`.
+    fn with_document(document: Document<'d>, ml: Language) -> Result<MarkupSth<'d>> {
+        let syntax = SyntaxConfig::from(ml.clone());
+        syntax.validate()?;
+        // Some defaults only apply to HTML and XML.
+        let markup = !matches!(ml, Language::Other(_));
+        let escape_text = !syntax.text_escapes.is_empty();
+        let raw_text_tags = match ml {
+            Language::Html | Language::Html5 | Language::XHtml => {
+                vec!["script".to_string(), "style".to_string()]
+            }
+            _ => Vec::new(),
+        };
+        let void_elements = match ml {
+            Language::Html | Language::Html5 | Language::XHtml => {
+                HTML_VOID_ELEMENTS.iter().map(|t| t.to_string()).collect()
+            }
+            _ => Vec::new(),
+        };
         Ok(MarkupSth {
-            syntax: SyntaxConfig::from(ml),
-            formatter: Box::new(crate::formatters::AutoIndent::new()),
+            name_rule: NameRule::from(&ml),
+            syntax,
+            formatter: default_formatter(&ml),
+            text_wrap_width: None,
+            assert_no_duplicate_attrs: false,
+            collapse_empty_pairs: false,
+            sort_attributes: false,
+            allowed_tags: None,
+            sanitize_mode: SanitizeMode::Error,
+            self_closing_slash: false,
+            verbatim_tag: None,
+            strict_close: false,
+            void_elements,
+            strict_void_elements: matches!(ml, Language::Html5 | Language::XHtml),
+            validate_names: false,
+            attr_quote_preference: AttrQuotes::Config,
+            escape_text,
+            raw_text_tags,
+            typography: false,
+            trim_policy: TrimPolicy::None,
+            ascii_only: false,
+            auto_cdata_threshold: None,
+            float_precision: None,
+            write_bom: false,
+            base_indent: 0,
+            warn_mixed_content: false,
+            single_root: matches!(ml, Language::Xml),
+            doctype_linefeed: markup,
             seq_state: SequenceState::new(),
+            root_done: false,
+            deferred_pair: false,
+            max_depth: None,
+            content_stack: Vec::new(),
+            mixed_content: Vec::new(),
+            cdata: matches!(ml, Language::Xml),
+            last_tag: String::new(),
+            attr_names: Vec::new(),
+            attrs_wrapped: false,
+            attr_column: 0,
+            skipped_tags: Vec::new(),
+            skip_properties: false,
+            document_started: false,
+            appended: false,
+            preserve_depth: None,
+            outer_formatters: Vec::new(),
+            stats: MarkupStats::default(),
             indent_str: String::new(),
             document,
         })
     }
 
-    /// Set a new `Formatter`.
+    /// Returns statistics about the elements, which have been written so far.
+    pub fn stats(&self) -> MarkupStats {
+        self.stats
+    }
+
+    /// Returns the document written so far, if `MarkupSth` writes into a `String`. Note, that the
+    /// last tag is not closed yet, since properties could still be added.
+    pub fn buffer(&self) -> Option<&str> {
+        self.document.as_str()
+    }
+
+    /// Returns the length of the document written so far in bytes. When writing into a `String`,
+    /// this is its length, otherwise the number of bytes written so far. Note, that the last tag is
+    /// not closed yet, since properties could still be added.
+    pub fn len(&self) -> usize {
+        self.document.len()
+    }
+
+    /// Returns whether nothing has been written into the document so far, see `len()`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns whether a tag named `tag` is currently open, e.g. whether we are inside a table.
+    pub fn is_inside(&self, tag: &str) -> bool {
+        self.seq_state.tag_stack.iter().any(|t| t == tag)
+    }
+
+    /// Returns how many tags named `tag` are currently open.
+    pub fn ancestor_count(&self, tag: &str) -> usize {
+        self.seq_state
+            .tag_stack
+            .iter()
+            .filter(|t| *t == tag)
+            .count()
+    }
+
+    /// Replaces the doctype by the XML declaration `declaration`, e.g. to change the encoding. This
+    /// is only possible before anything has been inserted.
+    pub fn set_xml_declaration(&mut self, declaration: &XmlDeclaration) -> Result<()> {
+        if self.seq_state.last.0 != Sequence::Initial || self.document_started {
+            return Err("MarkupSth: the XML declaration must be set before the first write".into());
+        }
+        self.syntax.doctype = Some(declaration.to_string());
+        Ok(())
+    }
+
+    /// Sets the maximum depth of nested tag pairs, e.g. to catch runaway recursion in generators.
+    /// Opening a tag, which would exceed this depth, returns `MarkupError::MaxDepthExceeded`.
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = Some(depth);
+    }
+
+    /// Replaces the doctype by `<!DOCTYPE root PUBLIC "public_id" "system_id">`, where the
+    /// identifiers are optional, e.g. `<!DOCTYPE root SYSTEM "system_id">` with a system identifier
+    /// only. This is only possible before anything has been inserted.
+    pub fn set_full_doctype(
+        &mut self,
+        root: &str,
+        public_id: Option<&str>,
+        system_id: Option<&str>,
+    ) -> Result<()> {
+        if self.seq_state.last.0 != Sequence::Initial || self.document_started {
+            return Err("MarkupSth: the doctype must be set before the first write".into());
+        }
+        let mut doctype = format!("<!DOCTYPE {}", root);
+        match (public_id, system_id) {
+            (Some(public_id), Some(system_id)) => {
+                write!(doctype, r#" PUBLIC "{}" "{}""#, public_id, system_id)?
+            }
+            (Some(public_id), None) => write!(doctype, r#" PUBLIC "{}""#, public_id)?,
+            (None, Some(system_id)) => write!(doctype, r#" SYSTEM "{}""#, system_id)?,
+            (None, None) => {}
+        }
+        doctype.push('>');
+        self.syntax.doctype = Some(doctype);
+        Ok(())
+    }
+
+    /// Returns the tags, which have received both text and tags as direct children, in order of
+    /// detection. Only recorded, if `warn_mixed_content` is set.
+    pub fn mixed_content(&self) -> &[String] {
+        &self.mixed_content
+    }
+
+    /// Sets a hook, which transforms each line of the document, when it is completed, e.g. to trim
+    /// trailing whitespaces. The last line is completed, when the document gets finalized. Note,
+    /// that `buffer()` does not contain the current, uncompleted line anymore.
+    pub fn set_line_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(&str) -> String + 'd,
+    {
+        self.document.set_line_hook(Box::new(hook) as LineHook<'d>);
+    }
+
+    /// Set a new `Formatter`. Once the document has been started, the indenting step size of the
+    /// current `Formatter` and the state of the document, i.e. its open tags and the current
+    /// indenting, will be taken over by the new one, so indenting stays consistent.
     pub fn set_formatter(&mut self, formatter: Box<dyn Formatter>) {
-        self.formatter = formatter;
+        self.swap_formatter(formatter);
+    }
+
+    /// Internal method to replace the `Formatter`, which returns the previous one. Once the
+    /// document has been started, the indenting step size and the state are synchronized.
+    fn swap_formatter(&mut self, mut formatter: Box<dyn Formatter>) -> Box<dyn Formatter> {
+        if self.seq_state.last.0 != Sequence::Initial {
+            formatter.set_indent_step_size(self.formatter.get_indent_step_size());
+            formatter.sync_state(&self.seq_state);
+        }
+        std::mem::replace(&mut self.formatter, formatter)
     }
 
     /// Inserts a single tag.
     pub fn self_closing(&mut self, tag: &str) -> Result<()> {
+        self.check_tag_name(tag)?;
+        if self.is_skipped(tag)? {
+            return Ok(());
+        }
+        if self.strict_void_elements && !self.void_elements.iter().any(|t| t == tag) {
+            return Err(MarkupError::NotVoidElement(tag.to_string()).into());
+        }
+        self.check_root(tag)?;
         self.finalize_last_op(TagSequence::self_closing(tag))?;
         if let Some(cfg) = &self.syntax.self_closing {
             self.document
                 .write_fmt(format_args!("{}{}", cfg.before, tag))?;
+            self.set_attr_column();
+            self.note_content(false);
+            self.root_done |= self.seq_state.tag_stack.is_empty();
+            self.stats.self_closed += 1;
             Ok(())
         } else {
             Err("MarkupSth: in this syntaxuration are no self-closing tag elements allowed".into())
@@ -116,33 +610,305 @@ impl<'d> MarkupSth<'d> {
     }
 
     pub fn open(&mut self, tag: &str) -> Result<()> {
+        self.check_tag_name(tag)?;
+        if self.is_skipped(tag)? {
+            self.skipped_tags.push(self.seq_state.tag_stack.len());
+            return Ok(());
+        }
+        self.check_root(tag)?;
+        if let Some(max) = self.max_depth {
+            if self.seq_state.tag_stack.len() >= max {
+                return Err(MarkupError::MaxDepthExceeded(max).into());
+            }
+        }
         self.finalize_last_op(TagSequence::opening(tag))?;
         if let Some(cfg) = &self.syntax.tag_pairs {
             self.document
                 .write_fmt(format_args!("{}{}", cfg.opening_before, tag))?;
+            self.set_attr_column();
+            self.note_content(false);
             self.seq_state.tag_stack.push(tag.to_string());
+            self.content_stack.push((false, false));
+            self.stats.opened += 1;
+            self.stats.max_depth = self.stats.max_depth.max(self.seq_state.tag_stack.len());
             Ok(())
         } else {
             Err("MarkupSth: in this syntaxuration are no tag-pair element allowed".into())
         }
     }
 
+    /// Opens a tag pair like `open()` and inserts `attrs` like `properties()` at once. The tag
+    /// stays open for its content, e.g. `<div class="box">`.
+    pub fn open_with(&mut self, tag: &str, attrs: &[(&str, &str)]) -> Result<()> {
+        self.open(tag)?;
+        self.properties(attrs)
+    }
+
+    /// Temporarily replaces the `Formatter` by `formatter` while running `f`, e.g. to insert a
+    /// `<pre>` block without any formatting. The previous `Formatter` is restored afterwards, even
+    /// if `f` fails. Formatting between two sequences is always decided by the `Formatter`, which
+    /// is active, when the latter one gets inserted. The previous `Formatter` still gets to know
+    /// all sequences inserted by `f`, so its state, e.g. the indenting stack of `AutoIndent`, stays
+    /// consistent. Afterwards, the indenting is restored to the one it would have applied.
+    pub fn with_formatter_scope<F>(&mut self, formatter: Box<dyn Formatter>, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
+        let previous = self.swap_formatter(formatter);
+        self.outer_formatters
+            .push((previous, self.seq_state.indent));
+        let result = f(self);
+        // The closure has no access to the outer formatters, so it cannot be missing.
+        let (previous, indent) = self.outer_formatters.pop().unwrap();
+        self.formatter = previous;
+        self.seq_state.indent = indent;
+        self.indent_str = " ".repeat(indent);
+        result
+    }
+
+    /// Runs `f` without any formatting, like with `NoFormatting`, e.g. to insert a compact inline
+    /// snippet into a pretty document. The `Formatter` and the indenting are restored afterwards,
+    /// even if `f` fails, see `with_formatter_scope()`.
+    pub fn raw_region<F>(&mut self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
+        self.with_formatter_scope(Box::new(NoFormatting::new()), f)
+    }
+
+    /// Inserts a conditional comment `<!--[if condition]>...<![endif]-->`, as used by legacy
+    /// templates for Internet Explorer, whose content is written by `f` and can contain tags. Both
+    /// markers are treated like text by the `Formatter`.
+    pub fn conditional_comment<F>(&mut self, condition: &str, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
+        self.finalize_last_op(TagSequence::text())?;
+        self.document
+            .write_fmt(format_args!("<!--[if {}]>", condition))?;
+        f(self)?;
+        self.finalize_last_op(TagSequence::text())?;
+        self.document.write_str("<![endif]-->")?;
+        Ok(())
+    }
+
+    /// Inserts a declaration `<!body>` at the top of the document after the doctype, e.g. an
+    /// `<!ENTITY ...>` declaration. Can be called multiple times, but only before the first tag or
+    /// text has been inserted. Each declaration is placed on its own line, if `doctype_linefeed`
+    /// is set.
+    pub fn declaration(&mut self, body: &str) -> Result<()> {
+        if self.seq_state.last.0 != Sequence::Initial {
+            return Err("MarkupSth: declarations can only be inserted before the root".into());
+        }
+        self.start_prologue()?;
+        if self.doctype_linefeed && self.document.column() > 0 {
+            self.document.write_char('\n')?;
+        }
+        self.document.write_fmt(format_args!("<!{}>", body))?;
+        Ok(())
+    }
+
+    /// Inserts an empty tag pair, e.g. `<div></div>` as a placeholder. Opening and closing tag are
+    /// always adjacent, the `Formatter` is consulted, but its changes between both are ignored. The
+    /// pair will never be collapsed (see `collapse_empty_pairs`).
+    pub fn open_close_empty(&mut self, tag: &str) -> Result<()> {
+        self.open(tag)?;
+        self.close_pair(true)
+    }
+
+    /// Applies `f` to each of the `items`, e.g. to render the entries of a list. Stops at the first
+    /// error and returns it.
+    pub fn repeat<T, F>(&mut self, items: impl IntoIterator<Item = T>, mut f: F) -> Result<()>
+    where
+        F: FnMut(&mut Self, T) -> Result<()>,
+    {
+        for item in items {
+            f(self, item)?;
+        }
+        Ok(())
+    }
+
+    /// Inserts a common HTML tag, typed by `tag`. Void elements (see `HtmlTag::is_void()`) are
+    /// inserted like `self_closing()`, all others are opened like `open()`.
+    pub fn open_tag(&mut self, tag: HtmlTag) -> Result<()> {
+        if tag.is_void() {
+            self.self_closing(tag.as_str())
+        } else {
+            self.open(tag.as_str())
+        }
+    }
+
+    /// Opens all given tags in order, e.g. for deeply nested wrappers.
+    pub fn open_many(&mut self, tags: &[&str]) -> Result<()> {
+        for tag in tags.iter() {
+            self.open(tag)?;
+        }
+        Ok(())
+    }
+
     pub fn close(&mut self) -> Result<()> {
+        self.close_pair(false)
+    }
+
+    /// Internal method to close the innermost tag pair. If `adjacent` is set, the closing tag
+    /// directly follows its opening tag: The pair will not be collapsed and the changes of the
+    /// `Formatter` are ignored, see `open_close_empty()`.
+    fn close_pair(&mut self, adjacent: bool) -> Result<()> {
         if self.syntax.tag_pairs.is_none() {
             return Err("MarkupSth: in this syntaxuration are no tag-pair element allowed".into());
         }
+        if self.skipped_tags.last() == Some(&self.seq_state.tag_stack.len()) {
+            self.skipped_tags.pop();
+            return Ok(());
+        }
         if self.seq_state.tag_stack.is_empty() {
-            return Err("MarkupSth: tag-pair tag_stack error".into());
+            if self.seq_state.last.0 == Sequence::SelfClosing {
+                return Err(format!(
+                    "MarkupSth: cannot close, no tag pair is open; the last tag '{}' is a \
+                     self-closing tag, which has no content and needs no closing, use open() for \
+                     tags with content",
+                    self.seq_state.last.1
+                )
+                .into());
+            }
+            return Err("MarkupSth: cannot close, no tag pair is open".into());
         }
 
+        let ends_preserve = self.preserve_depth == Some(self.seq_state.tag_stack.len());
         let tag = self.seq_state.tag_stack.pop().unwrap();
-        self.finalize_last_op(TagSequence::closing(&tag))?;
+        self.content_stack.pop();
+        self.root_done |= self.seq_state.tag_stack.is_empty();
+        let deferred = self.deferred_pair && self.can_self_close(&tag);
+        if !adjacent && (deferred || (self.collapse_empty_pairs && self.is_collapsible())) {
+            self.deferred_pair = false;
+            // The Formatter has seen the opening tag, so it gets to know the empty pair for a
+            // consistent state, but there is nothing in between to format.
+            if self.preserve_depth.is_none() {
+                self.seq_state.next = TagSequence::closing(&tag);
+                let _ = self.check_formatters();
+            }
+            // The opening tag has not been finalized yet, so it becomes a self-closing one.
+            self.seq_state.last.0 = Sequence::SelfClosing;
+            if ends_preserve {
+                self.preserve_depth = None;
+            }
+            return Ok(());
+        }
+        // The closing tag still belongs to the preserved region, so it gets no formatting.
+        self.finalize_last_op_with(TagSequence::closing(&tag), !adjacent)?;
+        if ends_preserve {
+            self.preserve_depth = None;
+        }
         let cfg = self.syntax.tag_pairs.as_ref().unwrap();
         self.document
             .write_fmt(format_args!("{}{}", cfg.closing_before, &tag))?;
         Ok(())
     }
 
+    /// Internal method to remember the column, wrapped properties of the just inserted tag will be
+    /// aligned to (the column of its first property).
+    fn set_attr_column(&mut self) {
+        let initiator = match self.syntax.properties.as_ref() {
+            Some(cfg) => display_width(&cfg.initiator.to_string()),
+            None => 0,
+        };
+        self.attr_column = self.document.column() + initiator;
+    }
+
+    /// Internal check method for `allowed_tags`, which returns whether `tag` shall be skipped.
+    fn is_skipped(&mut self, tag: &str) -> Result<bool> {
+        match &self.allowed_tags {
+            Some(allowed) if !allowed.contains(tag) => match self.sanitize_mode {
+                SanitizeMode::Error => Err(MarkupError::DisallowedTag(tag.to_string()).into()),
+                SanitizeMode::Skip => {
+                    self.skip_properties = true;
+                    Ok(true)
+                }
+            },
+            _ => Ok(false),
+        }
+    }
+
+    /// Internal check method for `single_root`, whether `tag` would be a second root element.
+    fn check_root(&self, tag: &str) -> Result<()> {
+        if self.single_root && self.root_done && self.seq_state.tag_stack.is_empty() {
+            return Err(MarkupError::MultipleRoots(tag.to_string()).into());
+        }
+        Ok(())
+    }
+
+    /// Internal check method for tag names, if `validate_names` is enabled.
+    fn check_tag_name(&self, tag: &str) -> Result<()> {
+        if self.validate_names && !self.name_rule.is_valid(tag) {
+            return Err(MarkupError::InvalidTagName(tag.to_string()).into());
+        }
+        Ok(())
+    }
+
+    /// Internal check method, if the last opening tag can be collapsed into a self-closing one.
+    fn is_collapsible(&self) -> bool {
+        if self.seq_state.last.0 != Sequence::Opening {
+            return false;
+        }
+        let tag = &self.seq_state.last.1;
+        if !self.void_elements.is_empty() && !self.void_elements.contains(tag) {
+            return false;
+        }
+        self.can_self_close(tag) && self.syntax_can_collapse()
+    }
+
+    /// Internal check method, if `tag` can be written as self-closing tag without leaving the
+    /// element open, e.g. `<span>` in HTML would be.
+    fn can_self_close(&self, tag: &str) -> bool {
+        self.void_elements.iter().any(|t| t == tag) || self.self_closing_closes()
+    }
+
+    /// Internal check method, if a self-closing tag closes its element in this syntax, which means
+    /// it differs from an opening tag, e.g. `<br/>`, but not `<br>`.
+    fn self_closing_closes(&self) -> bool {
+        if self.self_closing_slash {
+            return true;
+        }
+        match (&self.syntax.self_closing, &self.syntax.tag_pairs) {
+            (Some(sc), Some(tp)) => sc.after != tp.opening_after,
+            _ => false,
+        }
+    }
+
+    /// Internal check method, if the syntax provides self-closing tags with the same insertion
+    /// before the tag as opening tags, so an opening tag can become a self-closing one.
+    fn syntax_can_collapse(&self) -> bool {
+        match (&self.syntax.self_closing, &self.syntax.tag_pairs) {
+            (Some(sc), Some(tp)) => sc.before == tp.opening_before,
+            _ => false,
+        }
+    }
+
+    /// Inserts a tag, which will be decided to be a self-closing tag or a tag pair later on. The
+    /// opening tag is kept back like any other tag: If `close()` is the next call (properties
+    /// aside), it becomes a self-closing tag, e.g. `<node/>`, otherwise a tag pair with content,
+    /// e.g. `<node>Text</node>`. So `close()` has to be called in both cases. A tag, which would be
+    /// left open as self-closing tag, e.g. `<span>` in HTML, stays an empty tag pair.
+    ///
+    /// The `Formatter` gets to know an empty tag pair in the first case, so its state (e.g. the
+    /// indenting stack of `AutoIndent`) stays consistent, also when using the same tag name in both
+    /// kinds. Returns an error, if the syntax cannot turn opening tags into self-closing ones (see
+    /// `collapse_empty_pairs`).
+    pub fn self_closing_or_pair(&mut self, tag: &str) -> Result<()> {
+        if !self.syntax_can_collapse() {
+            return Err(format!(
+                "MarkupSth: tag '{}' cannot be decided to be self-closing by this syntax",
+                tag
+            )
+            .into());
+        }
+        let depth = self.seq_state.tag_stack.len();
+        self.open(tag)?;
+        // A skipped tag has not been opened.
+        self.deferred_pair = self.seq_state.tag_stack.len() > depth;
+        Ok(())
+    }
+
     /// TODO
     pub fn open_close_w(&mut self, tag: &str, content: &str) -> Result<()> {
         self.open(tag)?;
@@ -151,132 +917,911 @@ impl<'d> MarkupSth<'d> {
         Ok(())
     }
 
-    /// Inserts a single tag with properties.
+    /// Inserts a tag pair with content like `open_close_w()`, but the content will not be escaped,
+    /// e.g. for content, which has already been escaped (see `text_unchecked()`).
+    pub fn open_close_w_raw(&mut self, tag: &str, content: &str) -> Result<()> {
+        self.open(tag)?;
+        self.text_unchecked(content)?;
+        self.close()?;
+        Ok(())
+    }
+
+    /// Inserts properties into the last inserted tag.
+    ///
+    /// Properties will be written in the given order and will not be deduplicated, so the same
+    /// property name can be passed more than once, also when calling this method several times
+    /// for the same tag. To get an error in such a case, enable `assert_no_duplicate_attrs`. An
+    /// empty slice of properties changes nothing. A property with an empty value is written with
+    /// empty quotes, e.g. `value=""`.
+    ///
+    /// A tag pair with the property `xml:space="preserve"` keeps its whitespaces as written: The
+    /// `Formatter` will not insert any linefeeds or indenting until the tag gets closed.
     pub fn properties(&mut self, properties: &[(&str, &str)]) -> Result<()> {
-        if !matches!(
-            self.seq_state.last.0,
-            Sequence::SelfClosing | Sequence::Opening
-        ) {
-            return Err(
-                "MarkupSth: properties can only be added to self-closing or opening tags".into(),
-            );
+        if self.skip_properties {
+            return Ok(());
+        }
+        self.check_properties_target()?;
+        if properties.is_empty() {
+            return Ok(());
+        }
+        if self.validate_names {
+            for (name, _) in properties.iter() {
+                if !NameRule::Delimiters.is_valid(name) {
+                    return Err(MarkupError::InvalidAttributeName(name.to_string()).into());
+                }
+            }
+        }
+        if self.assert_no_duplicate_attrs {
+            self.check_duplicate_attrs(properties)?;
         }
 
-        if let Some(cfg) = &self.syntax.properties {
-            self.document.write_fmt(format_args!("{}", cfg.initiator))?;
-            let len = properties.len();
-            for property in properties[..len - 1].iter() {
-                self.document.write_fmt(format_args!(
-                    "{}{}{}{}{}{}{}{}",
-                    cfg.name_before,
-                    property.0,
-                    cfg.name_after,
-                    cfg.name_separator,
-                    cfg.value_before,
-                    property.1,
-                    cfg.value_after,
-                    cfg.value_separator
-                ))?;
-            }
-            let len = len - 1;
+        let cfg =
+            match self.syntax.properties.as_ref() {
+                Some(cfg) => cfg,
+                None => return Err(
+                    "MarkupSth: in this syntaxuration are no properties in tag elements allowed"
+                        .into(),
+                ),
+            };
+        let escaped: Vec<_> = properties
+            .iter()
+            .map(|(_, value)| {
+                let value = escape_with(value, &self.syntax.attr_escapes);
+                if !self.ascii_only {
+                    return value;
+                }
+                match escape_non_ascii(&value) {
+                    Cow::Owned(v) => Cow::Owned(v),
+                    Cow::Borrowed(_) => value,
+                }
+            })
+            .collect();
+        let mut quoted: Vec<_> = properties
+            .iter()
+            .zip(escaped.iter())
+            .map(|((name, _), value)| (*name, self.attr_quote_preference.quote(cfg, value)))
+            .collect();
+        if self.sort_attributes {
+            quoted.sort_by(|a, b| a.0.cmp(b.0));
+        }
+
+        // Check whether properties shall be wrapped, one per line.
+        let separator = match cfg.value_separator {
+            AttrSeparator::Inline(separator) => separator,
+            AttrSeparator::EachOnLine => {
+                self.attrs_wrapped = true;
+                Insertion::Nothing
+            }
+        };
+        if let Some(width) = self.formatter.get_attr_wrap_width() {
+            let len: usize = quoted
+                .iter()
+                .map(|(name, (value_before, value, value_after))| {
+                    display_width(&format!(
+                        "{}{}{}{}{}{}{}{}",
+                        separator,
+                        cfg.name_before,
+                        name,
+                        cfg.name_after,
+                        cfg.name_separator,
+                        value_before,
+                        value,
+                        value_after
+                    ))
+                })
+                .sum();
+            self.attrs_wrapped |= self.document.column() + len > width;
+        }
+
+        for (i, (name, (value_before, value, value_after))) in quoted.iter().enumerate() {
+            if self.attrs_wrapped && (i > 0 || !self.attr_names.is_empty()) {
+                self.document
+                    .write_fmt(format_args!("\n{}", " ".repeat(self.attr_column)))?;
+            } else if i > 0 {
+                self.document.write_fmt(format_args!("{}", separator))?;
+            } else {
+                self.document.write_fmt(format_args!("{}", cfg.initiator))?;
+            }
             self.document.write_fmt(format_args!(
                 "{}{}{}{}{}{}{}",
                 cfg.name_before,
-                properties[len].0,
+                name,
                 cfg.name_after,
                 cfg.name_separator,
-                cfg.value_before,
-                properties[len].1,
-                cfg.value_after,
+                value_before,
+                value,
+                value_after,
             ))?;
-            Ok(())
-        } else {
-            Err("MarkupSth: in this syntaxuration are no properties in tag elements allowed".into())
         }
+        self.attr_names
+            .extend(properties.iter().map(|(name, _)| name.to_string()));
+        if self.preserve_depth.is_none()
+            && self.seq_state.last.0 == Sequence::Opening
+            && properties.contains(&("xml:space", "preserve"))
+        {
+            self.preserve_depth = Some(self.seq_state.tag_stack.len());
+        }
+        Ok(())
     }
 
-    pub fn text(&mut self, text: &str) -> Result<()> {
-        self.finalize_last_op(TagSequence::text())?;
+    /// Inserts `text` into the last inserted tag as it is, separated like a property, e.g. a
+    /// pre-formatted `style="..."` or `srcset` property. There is neither a name nor a value
+    /// processing, so the caller is responsible for quoting and escaping.
+    pub fn raw_attr(&mut self, text: &str) -> Result<()> {
+        if self.skip_properties {
+            return Ok(());
+        }
+        self.check_properties_target()?;
+        let cfg =
+            match self.syntax.properties.as_ref() {
+                Some(cfg) => cfg,
+                None => return Err(
+                    "MarkupSth: in this syntaxuration are no properties in tag elements allowed"
+                        .into(),
+                ),
+            };
+        if self.attr_names.is_empty() {
+            self.document.write_fmt(format_args!("{}", cfg.initiator))?;
+        } else {
+            match cfg.value_separator {
+                AttrSeparator::Inline(separator) if !self.attrs_wrapped => {
+                    self.document.write_fmt(format_args!("{}", separator))?
+                }
+                _ => self
+                    .document
+                    .write_fmt(format_args!("\n{}", " ".repeat(self.attr_column)))?,
+            }
+        }
         self.document.write_str(text)?;
+        self.attr_names.push(text.to_string());
         Ok(())
     }
 
-    pub fn new_line(&mut self) -> Result<()> {
-        self.finalize_last_op(TagSequence::linefeed())?;
-        self.new_line_internal()?;
-        Ok(())
+    /// Internal check method, whether properties can be added, which requires the last sequence to
+    /// be a self-closing or an opening tag. Otherwise the error names the last inserted tag.
+    fn check_properties_target(&self) -> Result<()> {
+        let context = match self.seq_state.last.0 {
+            Sequence::SelfClosing | Sequence::Opening => return Ok(()),
+            Sequence::Closing => format!(", tag '{}' has already been closed", self.last_tag),
+            Sequence::Text | Sequence::LineFeed if !self.last_tag.is_empty() => {
+                format!(" after tag '{}'", self.last_tag)
+            }
+            _ => String::new(),
+        };
+        Err(format!(
+            "MarkupSth: properties can only be added to self-closing or opening tags, but last \
+             sequence was {:?}{}",
+            self.seq_state.last.0, context
+        )
+        .into())
     }
 
-    pub fn new_lines(&mut self, n: usize) -> Result<()> {
-        self.new_line()?;
-        for _ in 1..n {
-            self.new_line_internal()?;
-        }
-        Ok(())
+    /// Declares an XML namespace on the last inserted tag by inserting property `xmlns:prefix`, or
+    /// `xmlns` for an empty `prefix`. Declarations are written immediately, so they always appear
+    /// in the order of the calls, which keeps the output reproducible.
+    pub fn declare_namespace(&mut self, prefix: &str, uri: &str) -> Result<()> {
+        let name = if prefix.is_empty() {
+            "xmlns".to_string()
+        } else {
+            format!("xmlns:{}", prefix)
+        };
+        self.properties(&[(&name, uri)])
     }
 
-    pub fn indent_more(&mut self) -> Result<()> {
-        self.apply_format_changes(FormatChanges::indent_more(
-            self.seq_state.indent,
-            self.formatter.get_indent_step_size(),
-        ))?;
-        Ok(())
+    /// Inserts a single property `name`, whose value is formatted by its `Display` implementation,
+    /// e.g. a number like `width` or coordinates in SVG. Short values are formatted on the stack
+    /// without an intermediate `String`. Values must not contain any quotes.
+    pub fn attr_num(&mut self, name: &str, value: impl fmt::Display) -> Result<()> {
+        let mut buf = StackBuf::new();
+        if write!(buf, "{}", value).is_ok() {
+            self.properties(&[(name, buf.as_str())])
+        } else {
+            self.properties(&[(name, &value.to_string())])
+        }
     }
 
-    pub fn indent_less(&mut self) -> Result<()> {
-        self.apply_format_changes(FormatChanges::indent_less(
-            self.seq_state.indent,
-            self.formatter.get_indent_step_size(),
-        ))?;
-        Ok(())
+    /// Inserts a single property `name`, whose value is a float, rounded to `float_precision`.
+    pub fn attr_float(&mut self, name: &str, value: f64) -> Result<()> {
+        let mut buf = StackBuf::new();
+        if write_float(&mut buf, value, self.float_precision).is_ok() {
+            self.properties(&[(name, buf.as_str())])
+        } else {
+            let mut value_str = String::new();
+            write_float(&mut value_str, value, self.float_precision)?;
+            self.properties(&[(name, &value_str)])
+        }
     }
 
-    fn new_line_internal(&mut self) -> Result<()> {
-        self.document
-            .write_fmt(format_args!("\n{}", self.indent_str))?;
-        Ok(())
+    /// Inserts a single property `name`, whose value is built by joining all non-empty `values`
+    /// with `sep`, e.g. a `class` property of several conditional tokens. If all values are empty,
+    /// the property will be skipped entirely.
+    pub fn attr_list(&mut self, name: &str, values: &[&str], sep: &str) -> Result<()> {
+        let values: Vec<&str> = values.iter().copied().filter(|v| !v.is_empty()).collect();
+        if values.is_empty() {
+            return Ok(());
+        }
+        self.properties(&[(name, &values.join(sep))])
     }
 
-    pub fn close_all(&mut self) -> Result<()> {
-        for _ in 0..self.seq_state.tag_stack.len() {
-            self.close()?;
+    /// Inserts a single property `name`, whose value is a list of coordinate pairs, e.g. the
+    /// `points` of an SVG `polyline`. Coordinates of a pair are separated by a comma and pairs by a
+    /// space, e.g. `0,0 10.5,20`. The number of decimals can be limited by `float_precision`.
+    pub fn attr_points(&mut self, name: &str, points: &[(f64, f64)]) -> Result<()> {
+        let mut value = String::with_capacity(points.len() * 8);
+        for (i, (x, y)) in points.iter().enumerate() {
+            if i > 0 {
+                value.push(' ');
+            }
+            write_float(&mut value, *x, self.float_precision)?;
+            value.push(',');
+            write_float(&mut value, *y, self.float_precision)?;
         }
-        Ok(())
+        self.properties(&[(name, &value)])
     }
 
-    pub fn finalize(self) -> Result<()> {
-        match self.seq_state.last.0 {
-            Sequence::SelfClosing => final_op_arm!(selfclosing self),
-            Sequence::Opening => final_op_arm!(opening self),
-            Sequence::Closing => final_op_arm!(closing self),
-            _ => {}
+    /// Inserts a HTML5 data property `data-key="value"` into the last inserted tag. The value is
+    /// written like by `properties()`, i.e. `&`, `<` and the quote character get escaped. Returns
+    /// `MarkupError::InvalidAttributeName`, if `key` contains characters, which are not allowed in
+    /// property names.
+    pub fn data_attr(&mut self, key: &str, value: &str) -> Result<()> {
+        self.data_attrs(&[(key, value)])
+    }
+
+    /// Inserts multiple HTML5 data properties at once, see `data_attr()`.
+    pub fn data_attrs(&mut self, attrs: &[(&str, &str)]) -> Result<()> {
+        let mut names = Vec::with_capacity(attrs.len());
+        for (key, _) in attrs.iter() {
+            let name = format!("data-{}", key);
+            if key.is_empty() || !NameRule::Delimiters.is_valid(&name) {
+                return Err(MarkupError::InvalidAttributeName(name).into());
+            }
+            names.push(name);
         }
-        Ok(())
+        let properties: Vec<(&str, &str)> = names
+            .iter()
+            .zip(attrs.iter())
+            .map(|(n, (_, v))| (n.as_str(), *v))
+            .collect();
+        self.properties(&properties)
     }
 
-    /// This internal method finalizes the last operation, e.g. close the tag. Because the tag
-    /// elements will never be closed when inserting them, it has to be done later due to optional
-    /// properties, which can be added afterwards.
-    fn finalize_last_op(&mut self, next: TagSequence) -> Result<()> {
-        // Close last tag (maybe after we have added properties).
-        match self.seq_state.last.0 {
-            Sequence::Initial => {
-                if let Some(dt) = self.syntax.doctype.as_ref() {
-                    self.document.write_str(dt)?;
-                }
+    /// Inserts properties into the last inserted tag like `properties()`, but from an `Attributes`
+    /// map, in order of their insertion.
+    pub fn properties_map(&mut self, attrs: &Attributes) -> Result<()> {
+        let properties: Vec<(&str, &str)> = attrs.iter().collect();
+        self.properties(&properties)
+    }
+
+    /// Inserts properties into the last inserted tag like `properties()`, but from a `HashMap`.
+    /// The properties are sorted by name, so the output is reproducible, independent of the
+    /// iteration order of the `HashMap`.
+    pub fn properties_sorted(&mut self, attrs: &HashMap<String, String>) -> Result<()> {
+        let mut properties: Vec<(&str, &str)> = attrs
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.as_str()))
+            .collect();
+        properties.sort_unstable_by_key(|(n, _)| *n);
+        self.properties(&properties)
+    }
+
+    /// Inserts properties into the last inserted tag, which are parsed from a string of
+    /// space-separated `name=value` pairs, e.g. `class=box id=main`.
+    ///
+    /// Values with spaces can be quoted by `"` or `'`, e.g. `title="Main box"`. Bare names without
+    /// a value, e.g. `hidden`, are boolean properties and will be written with an empty value.
+    /// Malformed input, e.g. unterminated quotes or a missing name, results in an error.
+    pub fn properties_from_str(&mut self, s: &str) -> Result<()> {
+        let properties = parse_properties(s)?;
+        let properties: Vec<(&str, &str)> = properties
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.as_str()))
+            .collect();
+        self.properties(&properties)
+    }
+
+    /// Internal check method, if any of the property names has already been added to the current
+    /// tag, or appears twice in `properties`.
+    fn check_duplicate_attrs(&self, properties: &[(&str, &str)]) -> Result<()> {
+        for (i, (name, _)) in properties.iter().enumerate() {
+            if self.attr_names.iter().any(|n| n == name)
+                || properties[..i].iter().any(|(n, _)| n == name)
+            {
+                return Err(format!(
+                    "MarkupSth: property '{}' has already been added to tag '{}'",
+                    name, self.seq_state.last.1
+                )
+                .into());
             }
-            Sequence::SelfClosing => final_op_arm!(selfclosing self),
-            Sequence::Opening => final_op_arm!(opening self),
-            Sequence::Closing => final_op_arm!(closing self),
-            Sequence::Text | Sequence::LineFeed => {}
         }
-        self.seq_state.next = next.clone();
-        let check = self.formatter.check(&self.seq_state);
-        self.apply_format_changes(check)?;
-        self.seq_state.last = next;
         Ok(())
     }
 
+    /// Renders `value` into this document, see trait `Markup`.
+    pub fn render<T: Markup + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.render(self)
+    }
+
+    /// Writes a whole tree of nodes, by recursively inserting tags, properties and text of `node`.
+    pub fn write_node(&mut self, node: &Node) -> Result<()> {
+        match node {
+            Node::Element {
+                name,
+                attrs,
+                children,
+            } => {
+                self.open(name)?;
+                self.write_node_attrs(attrs)?;
+                for child in children.iter() {
+                    self.write_node(child)?;
+                }
+                self.close()?;
+            }
+            Node::Text(text) => self.text(text)?,
+            Node::SelfClosing { name, attrs } => {
+                self.self_closing(name)?;
+                self.write_node_attrs(attrs)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Internal method to write the properties of a `Node`.
+    fn write_node_attrs(&mut self, attrs: &[(String, String)]) -> Result<()> {
+        if attrs.is_empty() {
+            return Ok(());
+        }
+        let attrs: Vec<(&str, &str)> = attrs
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.as_str()))
+            .collect();
+        self.properties(&attrs)
+    }
+
+    /// Inserts text content. If `escape_text` is set, the text will be escaped, except within a
+    /// tag listed in `raw_text_tags`, where an error is returned if the text contains the closing
+    /// tag, e.g. `</script` in a `<script>`. If `typography` is set, typographic entities will be
+    /// substituted. Since `escape_text` is set by default for HTML and XML, text, which has already
+    /// been escaped, has to be inserted by `text_unchecked()` instead.
+    pub fn text(&mut self, text: &str) -> Result<()> {
+        let text = self.trim_policy.apply(text);
+        if let Some(cdata) = self.auto_cdata(text) {
+            return self.text_unchecked(&cdata);
+        }
+        let mut text = self.escaped_text(text)?;
+        if self.typography && self.raw_text_tag().is_none() {
+            if let Cow::Owned(t) = typography(&text) {
+                text = Cow::Owned(t);
+            }
+        }
+        if self.ascii_only && self.raw_text_tag().is_none() {
+            if let Cow::Owned(t) = escape_non_ascii(&text) {
+                text = Cow::Owned(t);
+            }
+        }
+        self.finalize_last_op(TagSequence::text())?;
+        if !text.trim().is_empty() {
+            self.note_content(true);
+        }
+        self.stats.text_nodes += 1;
+        match self.text_wrap_width {
+            Some(width) => self.write_wrapped(&text, width)?,
+            None => self.document.write_str(&text)?,
+        }
+        Ok(())
+    }
+
+    /// Internal method, which returns `text` wrapped into a CDATA section, if it exceeds
+    /// `auto_cdata_threshold`. A contained `]]>` gets split across two sections.
+    fn auto_cdata(&self, text: &str) -> Option<String> {
+        let threshold = self.auto_cdata_threshold?;
+        if !self.cdata || !self.escape_text || self.ascii_only || self.raw_text_tag().is_some() {
+            return None;
+        }
+        let escapes = &self.syntax.text_escapes;
+        let count = text
+            .chars()
+            .filter(|c| escapes.iter().any(|(e, _)| e == c))
+            .count();
+        if count <= threshold {
+            return None;
+        }
+        Some(format!(
+            "<![CDATA[{}]]>",
+            text.replace("]]>", "]]]]><![CDATA[>")
+        ))
+    }
+
+    /// Inserts text like `text()`, but without escaping, e.g. for numeric cells of large tables.
+    ///
+    /// **Warning:** The caller is responsible for `text` not containing any characters, which
+    /// would have to be escaped (`&`, `<` and `>`), otherwise the document will be broken.
+    pub fn text_unchecked(&mut self, text: &str) -> Result<()> {
+        self.finalize_last_op(TagSequence::text())?;
+        if !text.trim().is_empty() {
+            self.note_content(true);
+        }
+        self.stats.text_nodes += 1;
+        match self.text_wrap_width {
+            Some(width) => self.write_wrapped(text, width)?,
+            None => self.document.write_str(text)?,
+        }
+        Ok(())
+    }
+
+    /// Inserts an entity reference `&name;` as text, e.g. `entity("nbsp")` for `&nbsp;`. Returns
+    /// `MarkupError::InvalidEntityName`, if `name` is neither a named nor a numeric reference.
+    pub fn entity(&mut self, name: &str) -> Result<()> {
+        if !is_valid_entity_name(name) {
+            return Err(MarkupError::InvalidEntityName(name.to_string()).into());
+        }
+        self.finalize_last_op(TagSequence::text())?;
+        self.note_content(true);
+        self.stats.text_nodes += 1;
+        self.document.write_fmt(format_args!("&{};", name))?;
+        Ok(())
+    }
+
+    /// Inserts a verbatim block of multiple lines, e.g. a code block. The block is split into lines
+    /// and each line is placed on its own line with the current indenting. Lines won't be wrapped.
+    /// If `verbatim_tag` is set, the block will be wrapped by that tag.
+    pub fn verbatim_block(&mut self, content: &str) -> Result<()> {
+        if let Some(tag) = self.verbatim_tag.clone() {
+            self.open(&tag)?;
+        }
+        self.write_lines(content, true)?;
+        if self.verbatim_tag.is_some() {
+            self.close()?;
+        }
+        Ok(())
+    }
+
+    /// Inserts `fragment`, e.g. a sub-document generated by another `MarkupSth`, as it is, without
+    /// escaping. Each line after the first one gets re-indented to the current indenting, so the
+    /// relative indenting of the fragment is kept. Leading and trailing linefeeds of the fragment
+    /// are skipped. The fragment is treated like text by the `Formatter`.
+    pub fn embed(&mut self, fragment: &str) -> Result<()> {
+        self.write_lines(fragment.trim_matches('\n'), false)
+    }
+
+    /// Internal method, which inserts `content` line by line with the current indenting, see
+    /// `verbatim_block()` and `embed()`. Empty lines stay empty.
+    fn write_lines(&mut self, content: &str, escape: bool) -> Result<()> {
+        for (i, line) in content.split('\n').enumerate() {
+            let line = if escape {
+                self.escaped_text(line)?
+            } else {
+                Cow::Borrowed(line)
+            };
+            if i == 0 {
+                self.finalize_last_op(TagSequence::text())?;
+                if !content.trim().is_empty() {
+                    self.note_content(true);
+                }
+                self.stats.text_nodes += 1;
+                self.document.write_str(&line)?;
+            } else if line.is_empty() {
+                self.document.write_char('\n')?;
+            } else {
+                self.new_line_internal()?;
+                self.document.write_str(&line)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Internal method to track the direct children of the innermost open tag, which receives
+    /// either text (`is_text`) or a tag. Records the tag, if it has mixed content now.
+    fn note_content(&mut self, is_text: bool) {
+        let Some((text, tags)) = self.content_stack.last_mut() else {
+            return;
+        };
+        let was_mixed = *text && *tags;
+        if is_text {
+            *text = true;
+        } else {
+            *tags = true;
+        }
+        if self.warn_mixed_content && !was_mixed && *text && *tags {
+            if let Some(tag) = self.seq_state.tag_stack.last() {
+                self.mixed_content.push(tag.clone());
+            }
+        }
+    }
+
+    /// Internal method to escape text content, if enabled (see `escape_text`).
+    fn escaped_text<'t>(&self, text: &'t str) -> Result<Cow<'t, str>> {
+        if !self.escape_text {
+            Ok(Cow::Borrowed(text))
+        } else if let Some(tag) = self.raw_text_tag() {
+            if text
+                .to_lowercase()
+                .contains(&format!("</{}", tag.to_lowercase()))
+            {
+                return Err(format!(
+                    "MarkupSth: text inside '{}' must not contain '</{}'",
+                    tag, tag
+                )
+                .into());
+            }
+            Ok(Cow::Borrowed(text))
+        } else {
+            Ok(escape_with(text, &self.syntax.text_escapes))
+        }
+    }
+
+    /// Internal check method, if the innermost open tag is a tag with raw text content.
+    fn raw_text_tag(&self) -> Option<&str> {
+        self.seq_state
+            .tag_stack
+            .last()
+            .filter(|t| self.raw_text_tags.contains(t))
+            .map(|t| t.as_str())
+    }
+
+    /// Internal method to write text word by word, which inserts a linefeed between two words
+    /// whenever the next word would exceed the wrapping width `width` of the current line.
+    fn write_wrapped(&mut self, text: &str, width: usize) -> Result<()> {
+        let indent = self.base_indent + display_width(&self.indent_str);
+        let mut column = self.document.column();
+        for (i, word) in text.split(' ').enumerate() {
+            let word_width = display_width(word);
+            if i > 0 {
+                if column + 1 + word_width > width && column > indent {
+                    self.new_line_internal()?;
+                    column = indent;
+                } else {
+                    self.document.write_char(' ')?;
+                    column += 1;
+                }
+            }
+            self.document.write_str(word)?;
+            column += word_width;
+        }
+        Ok(())
+    }
+
+    pub fn new_line(&mut self) -> Result<()> {
+        self.finalize_last_op(TagSequence::linefeed())?;
+        self.new_line_internal()?;
+        Ok(())
+    }
+
+    /// Inserts a linefeed like `new_line()`, but only if the current line is not already empty
+    /// (except of indenting), so consecutive calls will not produce blank lines. At the very
+    /// beginning of a document nothing will be inserted.
+    pub fn new_line_if_needed(&mut self) -> Result<()> {
+        match self.seq_state.last.0 {
+            Sequence::Initial => Ok(()),
+            Sequence::Text | Sequence::LineFeed if self.document.is_line_blank() => Ok(()),
+            _ => self.new_line(),
+        }
+    }
+
+    pub fn new_lines(&mut self, n: usize) -> Result<()> {
+        self.new_line()?;
+        for _ in 1..n {
+            self.new_line_internal()?;
+        }
+        Ok(())
+    }
+
+    pub fn indent_more(&mut self) -> Result<()> {
+        self.apply_format_changes(FormatChanges::indent_more(
+            self.seq_state.indent,
+            self.formatter.get_indent_step_size(),
+        ))?;
+        Ok(())
+    }
+
+    pub fn indent_less(&mut self) -> Result<()> {
+        self.apply_format_changes(FormatChanges::indent_less(
+            self.seq_state.indent,
+            self.formatter.get_indent_step_size(),
+        ))?;
+        Ok(())
+    }
+
+    fn new_line_internal(&mut self) -> Result<()> {
+        debug_assert_eq!(self.seq_state.indent, self.indent_str.len());
+        self.document.write_char('\n')?;
+        self.write_base_indent()?;
+        self.document.write_str(&self.indent_str)?;
+        Ok(())
+    }
+
+    /// Internal method to insert the base indenting, see `base_indent`.
+    fn write_base_indent(&mut self) -> Result<()> {
+        if self.base_indent > 0 {
+            self.document
+                .write_fmt(format_args!("{:1$}", "", self.base_indent))?;
+        }
+        Ok(())
+    }
+
+    /// Closes the top `n` opened tags. Returns an error, if less than `n` tags are open.
+    pub fn close_many(&mut self, n: usize) -> Result<()> {
+        let open = self.seq_state.tag_stack.len() + self.skipped_tags.len();
+        if n > open {
+            return Err(
+                format!("MarkupSth: cannot close {} tags, only {} are open", n, open).into(),
+            );
+        }
+        for _ in 0..n {
+            self.close()?;
+        }
+        Ok(())
+    }
+
+    /// Closes all tags up to and including the innermost open tag `tag`, e.g. to leave a
+    /// `<section>` from deep inside. Returns an error, if no tag `tag` is open.
+    pub fn close_to(&mut self, tag: &str) -> Result<()> {
+        let Some(depth) = self.seq_state.tag_stack.iter().rposition(|t| t == tag) else {
+            return Err(format!("MarkupSth: cannot close to '{}', it is not open", tag).into());
+        };
+        while self.seq_state.tag_stack.len() > depth {
+            self.close()?;
+        }
+        Ok(())
+    }
+
+    /// Closes the innermost open tag only if it is `tag`, e.g. to tidy up an optional wrapper.
+    /// Returns whether it has been closed.
+    pub fn close_current_if(&mut self, tag: &str) -> Result<bool> {
+        if self.skipped_tags.last() == Some(&self.seq_state.tag_stack.len())
+            || self.seq_state.tag_stack.last().map(|t| t.as_str()) != Some(tag)
+        {
+            return Ok(false);
+        }
+        self.close()?;
+        Ok(true)
+    }
+
+    pub fn close_all(&mut self) -> Result<()> {
+        for _ in 0..self.seq_state.tag_stack.len() + self.skipped_tags.len() {
+            self.close()?;
+        }
+        Ok(())
+    }
+
+    /// Finalizes the document by closing the last operation and inserting the optional ending of
+    /// the `Formatter` (see `Formatter::on_document_end()`).
+    pub fn finalize(mut self) -> Result<()> {
+        self.finalize_document()
+    }
+
+    /// Internal method to finalize the document, shared by `finalize()` and `finish()`. If writing
+    /// into a file has failed, its I/O error is returned instead of a generic formatting error.
+    pub(crate) fn finalize_document(&mut self) -> Result<()> {
+        if self.strict_close && !self.seq_state.tag_stack.is_empty() {
+            return Err(MarkupError::UnclosedTags {
+                remaining: self.seq_state.tag_stack.clone(),
+            }
+            .into());
+        }
+        self.write_ending()
+            .map_err(|e| match self.document.take_io_error() {
+                Some(io_error) => io_error.into(),
+                None => e,
+            })
+    }
+
+    /// Internal method to write the ending of the document and to flush it, see
+    /// `finalize_document()`.
+    fn write_ending(&mut self) -> Result<()> {
+        match self.seq_state.last.0 {
+            Sequence::Initial if !self.document_started => self.start_document()?,
+            Sequence::SelfClosing => final_op_arm!(selfclosing self),
+            Sequence::Opening => final_op_arm!(opening self),
+            Sequence::Closing => final_op_arm!(closing self),
+            _ => {}
+        }
+        if let Some(end) = self.formatter.on_document_end() {
+            self.document.write_str(&end)?;
+        }
+        self.document.flush_line()?;
+        self.document.flush()?;
+        // Leave no indenting behind, whatever the formatter has applied.
+        self.apply_format_changes(FormatChanges {
+            new_line: false,
+            new_indent: Some(0),
+        })?;
+        Ok(())
+    }
+
+    /// Discards the document written so far and all state, e.g. open tags and the indenting, so a
+    /// new document can be generated into the same `String` with the same configuration and
+    /// `Formatter`. Only available, if `MarkupSth` writes into a `String`. Content, which has been
+    /// in the `String` before, is kept, e.g. a document continued by `append_to()` is continued
+    /// again without a doctype.
+    pub fn reset(&mut self) -> Result<()> {
+        if !self.document.clear() {
+            return Err("MarkupSth: reset() is only available when writing into a String".into());
+        }
+        self.formatter.reset_state();
+        for (outer, indent) in self.outer_formatters.iter_mut() {
+            outer.reset_state();
+            *indent = 0;
+        }
+        self.seq_state = SequenceState::new();
+        if self.appended {
+            self.seq_state.last = TagSequence::text();
+        }
+        self.indent_str.clear();
+        self.root_done = false;
+        self.deferred_pair = false;
+        self.content_stack.clear();
+        self.mixed_content.clear();
+        self.last_tag.clear();
+        self.attr_names.clear();
+        self.attrs_wrapped = false;
+        self.attr_column = 0;
+        self.skipped_tags.clear();
+        self.skip_properties = false;
+        self.document_started = false;
+        self.preserve_depth = None;
+        self.stats = MarkupStats::default();
+        debug_assert_eq!(self.seq_state.indent, self.indent_str.len());
+        Ok(())
+    }
+
+    /// Finalizes the document like `finalize()` and returns a copy of the finished document. This
+    /// simplifies one-shot generations. Only available, if `MarkupSth` writes into a `String`.
+    pub fn finish(mut self) -> Result<String> {
+        self.finalize_document()?;
+        match self.document.as_str() {
+            Some(document) => Ok(document.to_string()),
+            None => Err("MarkupSth: finish() is only available when writing into a String".into()),
+        }
+    }
+
+    /// Takes everything written into an owned buffer since the last call, see `buffered()`.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn take_buffer(&mut self) -> String {
+        self.document.take_buffer()
+    }
+
+    /// Returns a 64-bit FNV-1a hash of the document written so far, e.g. to detect whether a
+    /// regenerated document has changed without diffing. The hash is updated while writing, also
+    /// when writing into a file. Since tags are completed lazily, use `finalize_digest()` to get
+    /// the hash of the finalized document. Requires feature `digest`.
+    #[cfg(feature = "digest")]
+    pub fn digest(&self) -> u64 {
+        self.document.digest()
+    }
+
+    /// Finalizes the document like `finalize()` and returns the hash of the finalized document,
+    /// see `digest()`. Requires feature `digest`.
+    #[cfg(feature = "digest")]
+    pub fn finalize_digest(mut self) -> Result<u64> {
+        self.finalize_document()?;
+        Ok(self.document.digest())
+    }
+
+    /// Finalizes the document like `finalize()` and writes it to the file at `path`. The document is
+    /// written to a temporary file next to `path` first, which then gets renamed, so no partial
+    /// files remain on a crash. The name of the temporary file is unique, so concurrent writers of
+    /// the same `path` do not interfere. Only available, if `MarkupSth` writes into a `String`.
+    pub fn finalize_to_file(mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.finalize_document()?;
+        let document =
+            match self.document.as_str() {
+                Some(document) => document,
+                None => return Err(
+                    "MarkupSth: finalize_to_file() is only available when writing into a String"
+                        .into(),
+                ),
+            };
+        let path = path.as_ref();
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp_path = path.with_file_name(tmp_name);
+        if let Err(e) = fs::write(&tmp_path, document) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+        if let Err(e) = fs::rename(&tmp_path, path) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Internal method to start the document with the optional beginning of the `Formatter` (see
+    /// `Formatter::on_document_start()`). It is called on the first sequence and not already in
+    /// `new()`, because the `Formatter` can be replaced before anything has been written.
+    fn start_document(&mut self) -> Result<()> {
+        if let Some(start) = self.formatter.on_document_start() {
+            self.document.write_str(&start)?;
+        }
+        Ok(())
+    }
+
+    /// Internal method to write the beginning of the document and the doctype once, before the
+    /// first sequence or declaration.
+    fn start_prologue(&mut self) -> Result<()> {
+        if self.document_started {
+            return Ok(());
+        }
+        self.document_started = true;
+        if self.write_bom {
+            self.document.write_bom()?;
+        }
+        self.write_base_indent()?;
+        self.start_document()?;
+        if let Some(dt) = self.syntax.doctype.as_ref() {
+            self.document.write_str(dt)?;
+        }
+        Ok(())
+    }
+
+    /// This internal method finalizes the last operation, e.g. close the tag. Because the tag
+    /// elements will never be closed when inserting them, it has to be done later due to optional
+    /// properties, which can be added afterwards.
+    fn finalize_last_op(&mut self, next: TagSequence) -> Result<()> {
+        self.finalize_last_op_with(next, true)
+    }
+
+    /// Finalizes the last operation like `finalize_last_op()`. The `Formatter` is consulted in any
+    /// case, but its changes are only applied, if `format` is set.
+    fn finalize_last_op_with(&mut self, next: TagSequence, format: bool) -> Result<()> {
+        let prologue = self.syntax.doctype.is_some() || self.document_started;
+        // Close last tag (maybe after we have added properties).
+        match self.seq_state.last.0 {
+            Sequence::Initial => self.start_prologue()?,
+            Sequence::SelfClosing => final_op_arm!(selfclosing self),
+            Sequence::Opening => final_op_arm!(opening self),
+            Sequence::Closing => final_op_arm!(closing self),
+            Sequence::Text | Sequence::LineFeed => {}
+        }
+        let mut check = if self.preserve_depth.is_some() {
+            FormatChanges::nothing()
+        } else {
+            self.seq_state.next = next.clone();
+            self.check_formatters()
+        };
+        if self.seq_state.last.0 == Sequence::Initial && prologue {
+            check.new_line = self.doctype_linefeed;
+        }
+        if format {
+            self.apply_format_changes(check)?;
+        }
+        if matches!(
+            next.0,
+            Sequence::SelfClosing | Sequence::Opening | Sequence::Closing
+        ) {
+            self.last_tag.clone_from(&next.1);
+        }
+        self.seq_state.last = next;
+        self.deferred_pair = false;
+        self.attr_names.clear();
+        self.attrs_wrapped = false;
+        self.skip_properties = false;
+        Ok(())
+    }
+
+    /// Internal method, which asks the `Formatter` for changes between the last and the next
+    /// sequence of `seq_state`. Formatters of enclosing scopes (see `with_formatter_scope()`) get to
+    /// know both sequences as well, but only their indenting is tracked.
+    fn check_formatters(&mut self) -> FormatChanges {
+        let indent = self.seq_state.indent;
+        for (outer, outer_indent) in self.outer_formatters.iter_mut() {
+            self.seq_state.indent = *outer_indent;
+            if let Some(new_indent) = outer.check(&self.seq_state).new_indent {
+                *outer_indent = new_indent;
+            }
+        }
+        self.seq_state.indent = indent;
+        if self.formatter.is_noop() {
+            FormatChanges::nothing()
+        } else {
+            self.formatter.check(&self.seq_state)
+        }
+    }
+
     fn apply_format_changes(&mut self, changes: FormatChanges) -> Result<()> {
         if let Some(indent) = changes.new_indent {
             self.indent_str = " ".repeat(indent);
@@ -289,6 +1834,117 @@ impl<'d> MarkupSth<'d> {
     }
 }
 
+/// Internal method, which writes `value` rounded to `precision` decimals without trailing zeros, or
+/// in its shortest representation, see `MarkupSth::float_precision`.
+fn write_float(out: &mut impl Write, value: f64, precision: Option<usize>) -> fmt::Result {
+    let Some(precision) = precision else {
+        return write!(out, "{}", value);
+    };
+    let mut buf = StackBuf::new();
+    let formatted = if write!(buf, "{:.*}", precision, value).is_ok() {
+        Cow::Borrowed(buf.as_str())
+    } else {
+        Cow::Owned(format!("{:.*}", precision, value))
+    };
+    let trimmed = if formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &formatted
+    };
+    // Rounding may result in a negative zero, e.g. `-0.001`.
+    out.write_str(if trimmed == "-0" { "0" } else { trimmed })
+}
+
+/// Internal buffer on the stack for short formatted values, see `MarkupSth::attr_num()`. Writing
+/// fails, if the buffer is exceeded.
+struct StackBuf {
+    buf: [u8; 64],
+    len: usize,
+}
+
+impl StackBuf {
+    fn new() -> StackBuf {
+        StackBuf {
+            buf: [0; 64],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        // Only complete `str`s are written into the buffer, so this cannot fail.
+        std::str::from_utf8(&self.buf[..self.len]).unwrap()
+    }
+}
+
+impl Write for StackBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Internal parser for `MarkupSth::properties_from_str()`.
+fn parse_properties(s: &str) -> Result<Vec<(String, String)>> {
+    let err = |msg: &str| -> Result<Vec<(String, String)>> {
+        Err(format!("MarkupSth: cannot parse properties '{}': {}", s, msg).into())
+    };
+    let mut properties = Vec::new();
+    let mut chars = s.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=') {
+            if c == '"' || c == '\'' {
+                return err("quote in property name");
+            }
+            name.push(c);
+        }
+        if name.is_empty() {
+            return err("missing property name");
+        }
+        if chars.next_if_eq(&'=').is_none() {
+            properties.push((name, String::new()));
+            continue;
+        }
+        let mut value = String::new();
+        match chars.next_if(|c| *c == '"' || *c == '\'') {
+            Some(quote) => {
+                loop {
+                    match chars.next() {
+                        Some(c) if c == quote => break,
+                        Some(c) => value.push(c),
+                        None => return err("unterminated quote"),
+                    }
+                }
+                if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+                    return err("missing whitespace after quoted value");
+                }
+            }
+            None => {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    value.push(c);
+                }
+                if value.is_empty() {
+                    return err("missing property value");
+                }
+            }
+        }
+        properties.push((name, value));
+    }
+    if properties.is_empty() {
+        return err("no properties");
+    }
+    Ok(properties)
+}
+
 /// Simplifies using `MarkupSth::properties()` and calls this method internally.
 #[macro_export]
 macro_rules! properties {
@@ -296,3 +1952,2714 @@ macro_rules! properties {
         $markup.properties(&[$(($name, $value)),*])
     }};
 }
+
+/// Simplifies using `MarkupSth::text()` with formatted text and calls this method internally. The
+/// formatted text will be escaped like any other text.
+///
+/// ```
+/// use markupsth::{text_fmt, Formatter, Language, MarkupSth, NoFormatting};
+///
+/// let mut document = String::new();
+/// let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+/// mus.set_formatter(Box::new(NoFormatting::new()));
+/// mus.open("p").unwrap();
+/// text_fmt!(mus, "{} < {}", 1, 2).unwrap();
+/// mus.close().unwrap();
+/// mus.finalize().unwrap();
+/// assert_eq!(document, "<!DOCTYPE html>\n<p>1 &lt; 2</p>");
+/// ```
+#[macro_export]
+macro_rules! text_fmt {
+    ($markup:expr, $($arg:tt)*) => {{
+        $markup.text(&format!($($arg)*))
+    }};
+}
+
+/// Inserts a complete element by calling `MarkupSth::open()`, `MarkupSth::properties()`, the
+/// body and `MarkupSth::close()`, so opening and closing are always balanced. Errors are propagated
+/// by `?`, so it can only be used in functions returning a `Result`.
+///
+/// ```
+/// use markupsth::{element, Language, MarkupSth};
+///
+/// fn list(mus: &mut MarkupSth) -> markupsth::Result<()> {
+///     element!(mus, "ul", { "class" => "list" }, {
+///         element!(mus, "li", {}, { mus.text("Item")?; });
+///     });
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! element {
+    ($markup:expr, $tag:expr, { $($name:expr => $value:expr),* $(,)? }, $body:block) => {{
+        $markup.open($tag)?;
+        $markup.properties(&[$(($name, $value)),*])?;
+        $body
+        $markup.close()?;
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        format::AutoFmtRule,
+        formatters::{
+            AlignClosing, AlwaysIndentAlwaysLf, Canonical, HtmlBlockIndent, MaxBlankLines,
+            NoFormatting,
+        },
+    };
+    use totems::assert_err;
+
+    /// Test formatter, which adds a leading and a trailing comment to the document.
+    #[derive(Debug)]
+    struct BannerFormatter;
+
+    impl Formatter for BannerFormatter {
+        fn new() -> BannerFormatter {
+            BannerFormatter
+        }
+
+        fn check(&mut self, _: &SequenceState) -> FormatChanges {
+            FormatChanges::nothing()
+        }
+
+        fn on_document_start(&mut self) -> Option<String> {
+            Some("<!-- generated by markupsth -->\n".to_string())
+        }
+
+        fn on_document_end(&mut self) -> Option<String> {
+            Some("\n<!-- end -->".to_string())
+        }
+    }
+
+    #[test]
+    fn properties_are_not_deduplicated() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+
+        mus.open("div").unwrap();
+        properties!(mus, "class", "a", "id", "x", "class", "b").unwrap();
+        properties!(mus, "id", "y").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                r#"<div class="a" id="x" class="b" id="y"></div>"#
+            ]
+        );
+    }
+
+    #[test]
+    fn properties_strict_mode_rejects_duplicates() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.assert_no_duplicate_attrs = true;
+
+        // Duplicates within one call, and across two calls for the same tag.
+        mus.open("div").unwrap();
+        assert_err!(properties!(mus, "class", "a", "class", "b"));
+        mus.open("div").unwrap();
+        properties!(mus, "class", "a").unwrap();
+        assert_err!(properties!(mus, "class", "b"));
+
+        // Same names on different tags are fine.
+        mus.open("div").unwrap();
+        properties!(mus, "class", "a", "id", "x").unwrap();
+        mus.self_closing("img").unwrap();
+        properties!(mus, "class", "a", "id", "x").unwrap();
+    }
+
+    #[test]
+    fn collapse_empty_pairs_disabled() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+
+        mus.open("root").unwrap();
+        mus.open("empty").unwrap();
+        properties!(mus, "id", "1").unwrap();
+        mus.close().unwrap();
+        mus.open_close_w("full", "Text").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                "\n",
+                r#"<root><empty id="1"></empty><full>Text</full></root>"#
+            ]
+        );
+    }
+
+    #[test]
+    fn collapse_empty_pairs_enabled() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.collapse_empty_pairs = true;
+
+        mus.open("root").unwrap();
+        mus.open("empty").unwrap();
+        properties!(mus, "id", "1").unwrap();
+        mus.close().unwrap();
+        mus.open_close_w("full", "Text").unwrap();
+        mus.open("last").unwrap();
+        mus.close().unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                "\n",
+                r#"<root><empty id="1" /><full>Text</full><last /></root>"#
+            ]
+        );
+    }
+
+    #[test]
+    fn collapse_empty_pairs_with_auto_indent() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.collapse_empty_pairs = true;
+        let fmtr = mus.formatter.get_ext_auto_indenting().unwrap();
+        fmtr.add_tags_to_rule(&["root"], AutoFmtRule::IndentAlways)
+            .unwrap();
+        fmtr.add_tags_to_rule(&["empty"], AutoFmtRule::LfClosing)
+            .unwrap();
+
+        mus.open("root").unwrap();
+        mus.open("empty").unwrap();
+        mus.close().unwrap();
+        mus.open("empty").unwrap();
+        mus.close().unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                "\n<root>\n    <empty />\n    <empty />\n</root>"
+            ]
+        );
+    }
+
+    #[test]
+    fn collapse_empty_pairs_html_and_formatter_state() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.collapse_empty_pairs = true;
+        mus.open("p").unwrap();
+        mus.open("span").unwrap();
+        mus.close().unwrap();
+        mus.open("br").unwrap();
+        mus.close().unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(document, "<!DOCTYPE html>\n<p><span></span><br></p>");
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.set_formatter(Box::new(AlignClosing::new()));
+        mus.collapse_empty_pairs = true;
+        mus.open("root").unwrap();
+        mus.open("list").unwrap();
+        mus.open("empty").unwrap();
+        mus.close().unwrap();
+        mus.open("item").unwrap();
+        mus.text("Text").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            concat![
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                "\n<root>\n    <list>\n        <empty />\n        <item>Text</item>\n    </list>\n</root>"
+            ]
+        );
+    }
+
+    #[test]
+    fn finish_returns_document() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open("p").unwrap();
+        mus.text("Text").unwrap();
+        mus.close().unwrap();
+        let html = mus.finish().unwrap();
+
+        assert_eq!(html, "<!DOCTYPE html>\n<p>Text</p>");
+        assert_eq!(document, html);
+    }
+
+    #[test]
+    fn stats_count_written_elements() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        assert_eq!(mus.stats(), MarkupStats::default());
+
+        mus.open("html").unwrap();
+        mus.open("body").unwrap();
+        mus.open("div").unwrap();
+        mus.self_closing("img").unwrap();
+        mus.text("Text").unwrap();
+        mus.close().unwrap();
+        mus.open_close_w("p", "More text").unwrap();
+        mus.self_closing("br").unwrap();
+        mus.close_all().unwrap();
+
+        assert_eq!(
+            mus.stats(),
+            MarkupStats {
+                opened: 4,
+                self_closed: 2,
+                text_nodes: 2,
+                max_depth: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn attribute_names_not_validated_by_default() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.self_closing("img").unwrap();
+        properties!(mus, "data src", "image.jpg").unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat!["<!DOCTYPE html>\n", r#"<img data src="image.jpg">"#]
+        );
+    }
+
+    #[test]
+    fn attribute_names_validated() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.validate_names = true;
+
+        mus.self_closing("img").unwrap();
+        properties!(mus, "src", "image.jpg", "data-id", "1", "xml:lang", "en").unwrap();
+        for name in [
+            "data src", "a<b", "a>b", "a/b", "a=b", "a\"b", "a'b", "a\tb", "",
+        ] {
+            let err = mus.properties(&[(name, "value")]).unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<MarkupError>(),
+                Some(&MarkupError::InvalidAttributeName(name.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn tag_names_not_validated_by_default() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open("div class").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(document, "<!DOCTYPE html>\n<div class></div class>");
+    }
+
+    #[test]
+    fn tag_names_validated() {
+        let invalid = |err: Box<dyn std::error::Error>, name: &str| {
+            assert_eq!(
+                err.downcast_ref::<MarkupError>(),
+                Some(&MarkupError::InvalidTagName(name.to_string()))
+            );
+        };
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.validate_names = true;
+        mus.open("div").unwrap();
+        mus.self_closing("my-element").unwrap();
+        invalid(mus.open("div class").unwrap_err(), "div class");
+        invalid(mus.self_closing("img/").unwrap_err(), "img/");
+        invalid(mus.open("größe").unwrap_err(), "größe");
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.validate_names = true;
+        mus.open("größe").unwrap();
+        mus.self_closing("xs:element").unwrap();
+        invalid(mus.open("1st").unwrap_err(), "1st");
+        invalid(mus.self_closing("a b").unwrap_err(), "a b");
+    }
+
+    /// Test implementor of `std::fmt::Write`, which counts the written pieces.
+    #[derive(Default)]
+    struct CountingWriter {
+        content: String,
+        writes: usize,
+    }
+
+    impl Write for CountingWriter {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.content.push_str(s);
+            self.writes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_into_custom_writer() {
+        let mut writer = CountingWriter::default();
+        let mut mus = MarkupSth::from_writer(&mut writer, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.text_wrap_width = Some(20);
+        mus.open("p").unwrap();
+        properties!(mus, "class", "text").unwrap();
+        mus.text("This is some text to be wrapped").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            writer.content,
+            "<!DOCTYPE html>\n<p class=\"text\">This\nis some text to be\nwrapped</p>"
+        );
+        assert!(writer.writes > 0);
+    }
+
+    #[test]
+    fn finish_requires_string_document() {
+        let mut writer = CountingWriter::default();
+        let mus = MarkupSth::from_writer(&mut writer, Language::Html).unwrap();
+        assert!(mus.finish().is_err());
+    }
+
+    #[test]
+    fn properties_from_str() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open("div").unwrap();
+        mus.properties_from_str("class=box id=main").unwrap();
+        mus.self_closing("input").unwrap();
+        mus.properties_from_str(r#"  title="Main box"  disabled value='one two' "#)
+            .unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                r#"<div class="box" id="main">"#,
+                r#"<input title="Main box" disabled="" value="one two"></div>"#
+            ]
+        );
+    }
+
+    #[test]
+    fn properties_from_str_malformed() {
+        for s in [
+            "",
+            "   ",
+            "=value",
+            "class=",
+            "class= id=main",
+            r#"title="unterminated"#,
+            r#"title="a"b"#,
+            r#"ti"tle=a"#,
+        ] {
+            assert_err!(parse_properties(s));
+        }
+    }
+
+    #[test]
+    fn attr_quote_preference() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.attr_quote_preference = AttrQuotes::Prefer('"');
+        mus.self_closing("input").unwrap();
+        properties!(
+            mus,
+            "a",
+            "neither",
+            "b",
+            r#"only "double""#,
+            "c",
+            "only 'single'",
+            "d",
+            r#"it's "both""#
+        )
+        .unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                r#"<input a="neither" b='only "double"' "#,
+                r#"c="only 'single'" d="it's &quot;both&quot;">"#
+            ]
+        );
+    }
+
+    #[test]
+    fn text_escaped_except_in_script_and_style() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open("script").unwrap();
+        mus.text("if (a < b && b > c) { run(); }").unwrap();
+        mus.close().unwrap();
+        mus.open("style").unwrap();
+        mus.text("p > span { color: red; }").unwrap();
+        mus.close().unwrap();
+        mus.open("p").unwrap();
+        mus.text("a < b && b > c").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n<script>if (a < b && b > c) { run(); }</script>",
+                "<style>p > span { color: red; }</style>",
+                "<p>a &lt; b &amp;&amp; b &gt; c</p>"
+            ]
+        );
+    }
+
+    #[test]
+    fn text_in_script_must_not_close_it() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.open("script").unwrap();
+        assert_err!(mus.text("document.write('</script>');"));
+        assert_err!(mus.text("document.write('</SCRIPT>');"));
+        mus.text("document.write('<p>');").unwrap();
+    }
+
+    #[test]
+    fn text_not_escaped_if_disabled() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.escape_text = false;
+        mus.open("p").unwrap();
+        mus.text("<b>bold</b>").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(document, "<!DOCTYPE html>\n<p><b>bold</b></p>");
+    }
+
+    #[test]
+    fn new_line_if_needed_avoids_blank_lines() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.new_line_if_needed().unwrap();
+        mus.open("div").unwrap();
+        mus.new_line_if_needed().unwrap();
+        mus.new_line_if_needed().unwrap();
+        mus.text("Text").unwrap();
+        mus.new_line_if_needed().unwrap();
+        mus.new_line_if_needed().unwrap();
+        mus.close().unwrap();
+        mus.new_line_if_needed().unwrap();
+        mus.new_line().unwrap();
+        mus.new_line_if_needed().unwrap();
+        mus.self_closing("br").unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(document, "<!DOCTYPE html>\n<div>\nText\n</div>\n\n<br>");
+    }
+
+    #[test]
+    fn formatter_document_start_and_end() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(BannerFormatter::new()));
+
+        mus.open("p").unwrap();
+        mus.text("Text").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            "<!-- generated by markupsth -->\n<!DOCTYPE html>\n<p>Text</p>\n<!-- end -->"
+        );
+    }
+
+    #[test]
+    fn formatter_document_start_on_empty_document() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(BannerFormatter::new()));
+        mus.finalize().unwrap();
+
+        assert_eq!(document, "<!-- generated by markupsth -->\n\n<!-- end -->");
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    fn text_wrapping_counts_wide_characters_twice() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.text_wrap_width = Some(12);
+
+        mus.open("p").unwrap();
+        mus.text("漢字 漢字 漢字 漢字 漢字").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        // "<p>" takes three columns, each word "漢字" takes four columns.
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<p>漢字 漢字\n漢字 漢字\n漢字</p>"
+        );
+        for line in document.lines().skip(1) {
+            assert!(display_width(line) <= 12);
+        }
+    }
+
+    #[test]
+    fn text_wrapping_keeps_current_indenting() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.text_wrap_width = Some(11);
+
+        mus.open("div").unwrap();
+        mus.indent_more().unwrap();
+        mus.new_line().unwrap();
+        mus.text("aaa bbb ccc").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<div>\n    aaa bbb\n    ccc</div>"
+        );
+    }
+
+    #[test]
+    fn text_without_wrapping_is_written_as_it_is() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+
+        mus.open("p").unwrap();
+        mus.text("漢字 漢字 漢字 漢字 漢字").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(document, "<!DOCTYPE html>\n<p>漢字 漢字 漢字 漢字 漢字</p>");
+    }
+
+    #[test]
+    fn doctype_linefeed_independent_of_formatter() {
+        let write = |formatter: Box<dyn Formatter>, doctype_linefeed: bool| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+            mus.set_formatter(formatter);
+            mus.doctype_linefeed = doctype_linefeed;
+            mus.open("p").unwrap();
+            mus.close().unwrap();
+            mus.finalize().unwrap();
+            document
+        };
+
+        assert_eq!(
+            write(Box::new(NoFormatting::new()), true),
+            "<!DOCTYPE html>\n<p></p>"
+        );
+        assert_eq!(
+            write(Box::new(NoFormatting::new()), false),
+            "<!DOCTYPE html><p></p>"
+        );
+        assert_eq!(
+            write(Box::new(AlwaysIndentAlwaysLf::new()), false),
+            "<!DOCTYPE html><p>\n</p>"
+        );
+    }
+
+    #[test]
+    fn open_many_and_close_many() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+
+        mus.open("body").unwrap();
+        mus.open_many(&["div", "div", "span"]).unwrap();
+        assert_eq!(mus.seq_state.tag_stack, vec!["body", "div", "div", "span"]);
+        mus.text("Text").unwrap();
+        mus.close_many(2).unwrap();
+        assert_eq!(mus.seq_state.tag_stack, vec!["body", "div"]);
+        assert_err!(mus.close_many(3));
+        assert_eq!(mus.seq_state.tag_stack, vec!["body", "div"]);
+        mus.close_many(2).unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<body><div><div><span>Text</span></div></div></body>"
+        );
+    }
+
+    #[test]
+    fn properties_error_names_last_sequence() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.open("p").unwrap();
+        mus.text("Text").unwrap();
+
+        let err = mus.properties(&[("class", "text")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "MarkupSth: properties can only be added to self-closing or opening tags, but last \
+             sequence was Text after tag 'p'"
+        );
+    }
+
+    #[test]
+    fn properties_error_names_offending_tag() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.open("p").unwrap();
+        mus.close().unwrap();
+        let err = mus.properties(&[("class", "text")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "MarkupSth: properties can only be added to self-closing or opening tags, but last \
+             sequence was Closing, tag 'p' has already been closed"
+        );
+
+        mus.self_closing("img").unwrap();
+        mus.text("Text").unwrap();
+        let err = mus.properties(&[("src", "a.png")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "MarkupSth: properties can only be added to self-closing or opening tags, but last \
+             sequence was Text after tag 'img'"
+        );
+
+        mus.self_closing("br").unwrap();
+        mus.new_line().unwrap();
+        assert_err!(mus.properties(&[("class", "x")]));
+        mus.open("div").unwrap();
+        mus.self_closing("hr").unwrap();
+        mus.close().unwrap();
+        let err = mus.raw_attr("hidden").unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("tag 'div' has already been closed"));
+    }
+
+    #[test]
+    fn finalize_to_file_writes_document() {
+        let dir = std::env::temp_dir().join(format!("markupsth-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("index.html");
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.open("p").unwrap();
+        mus.text("Text").unwrap();
+        mus.close().unwrap();
+        mus.finalize_to_file(&path).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, document);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Concurrent writers of the same path use their own temporary files.
+        let writers: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let mut document = String::new();
+                    let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+                    mus.doctype_linefeed = false;
+                    mus.open_close_w("p", &"x".repeat(i * 1000)).unwrap();
+                    mus.finalize_to_file(&path).unwrap();
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("<!DOCTYPE html><p>") && written.ends_with("</p>"));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        // A failed rename leaves no temporary file behind.
+        let target = dir.join("target");
+        std::fs::create_dir(&target).unwrap();
+        std::fs::write(target.join("child"), "").unwrap();
+        let mut document = String::new();
+        let mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        assert!(mus.finalize_to_file(&target).is_err());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn append_to_existing_document() {
+        let mut document = String::from("<!DOCTYPE html>\n<p>Existing</p>");
+        let mut mus = MarkupSth::append_to(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open("p").unwrap();
+        mus.text("New").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(document, "<!DOCTYPE html>\n<p>Existing</p><p>New</p>");
+    }
+
+    #[test]
+    fn formatter_scope_for_pre_block() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+
+        mus.open("body").unwrap();
+        mus.open("div").unwrap();
+        mus.open("pre").unwrap();
+        mus.with_formatter_scope(Box::new(NoFormatting::new()), |mus| {
+            mus.text("line 1\n  line 2")?;
+            mus.close()
+        })
+        .unwrap();
+        mus.open("p").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                "<body>\n",
+                "    <div>\n",
+                "        <pre>line 1\n  line 2</pre>\n",
+                "        <p>\n",
+                "        </p>\n",
+                "    </div>\n",
+                "</body>"
+            ]
+        );
+    }
+
+    #[test]
+    fn xml_space_preserve_suppresses_formatting() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+
+        mus.open("doc").unwrap();
+        mus.open("code").unwrap();
+        properties!(mus, "xml:space", "preserve").unwrap();
+        mus.text("let x = 1;").unwrap();
+        mus.open("b").unwrap();
+        mus.open("i").unwrap();
+        properties!(mus, "xml:space", "preserve").unwrap();
+        mus.text("bold").unwrap();
+        mus.close().unwrap();
+        mus.close().unwrap();
+        mus.self_closing("br").unwrap();
+        mus.close().unwrap();
+        mus.open("p").unwrap();
+        mus.text("formatted").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+                "<doc>\n",
+                "    <code xml:space=\"preserve\">let x = 1;<b><i xml:space=\"preserve\">bold</i></b><br /></code>\n",
+                "    <p>\n",
+                "        formatted\n",
+                "    </p>\n",
+                "</doc>"
+            ]
+        );
+    }
+
+    #[test]
+    fn xml_space_preserve_ends_with_collapsed_tag() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+        mus.collapse_empty_pairs = true;
+
+        mus.open("doc").unwrap();
+        mus.open("space").unwrap();
+        properties!(mus, "xml:space", "preserve").unwrap();
+        mus.close().unwrap();
+        mus.open("p").unwrap();
+        mus.open("q").unwrap();
+        properties!(mus, "xml:space", "default").unwrap();
+        mus.text("x").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+                "<doc>\n",
+                "    <space xml:space=\"preserve\" />\n",
+                "    <p>\n",
+                "        <q xml:space=\"default\">\n",
+                "            x\n",
+                "        </q>\n",
+                "    </p>\n",
+                "</doc>"
+            ]
+        );
+    }
+
+    #[test]
+    fn formatter_scope_keeps_auto_indent_state() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(HtmlBlockIndent::new()));
+
+        mus.open("body").unwrap();
+        mus.with_formatter_scope(Box::new(NoFormatting::new()), |mus| {
+            mus.open("pre")?;
+            mus.text("line 1\n  line 2")?;
+            mus.close()
+        })
+        .unwrap();
+        mus.open("div").unwrap();
+        mus.open_close_w("p", "Text").unwrap();
+        mus.close().unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                "<body><pre>line 1\n  line 2</pre>\n",
+                "    <div>\n",
+                "        <p>Text</p>\n",
+                "    </div>\n",
+                "</body>"
+            ]
+        );
+    }
+
+    #[test]
+    fn close_outer_tag_in_auto_indent_scope() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.doctype_linefeed = false;
+        mus.open("div").unwrap();
+        mus.text("a").unwrap();
+        mus.with_formatter_scope(Box::new(AutoIndent::new()), |mus| mus.close())
+            .unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(document, "<!DOCTYPE html><div>a</div>");
+    }
+
+    #[test]
+    fn html5_and_xhtml_self_closing_syntax() {
+        let write = |ml: Language| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::new(&mut document, ml).unwrap();
+            mus.set_formatter(Box::new(NoFormatting::new()));
+            mus.doctype_linefeed = false;
+            mus.collapse_empty_pairs = true;
+            mus.open("p").unwrap();
+            mus.self_closing("img").unwrap();
+            properties!(mus, "src", "a.png").unwrap();
+            mus.open("br").unwrap();
+            mus.close().unwrap();
+            mus.open("span").unwrap();
+            mus.close().unwrap();
+            mus.close().unwrap();
+            mus.finalize().unwrap();
+            document
+        };
+
+        assert_eq!(
+            write(Language::Html5),
+            r#"<!DOCTYPE html><p><img src="a.png"><br><span></span></p>"#
+        );
+        assert_eq!(
+            write(Language::XHtml),
+            concat![
+                r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "#,
+                r#""http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd">"#,
+                r#"<p><img src="a.png" /><br /><span></span></p>"#
+            ]
+        );
+        assert_eq!(
+            write(Language::Html),
+            r#"<!DOCTYPE html><p><img src="a.png"><br><span></span></p>"#
+        );
+    }
+
+    #[test]
+    fn strict_void_elements_of_html5() {
+        for ml in [Language::Html5, Language::XHtml] {
+            let mut document = String::new();
+            let mut mus = MarkupSth::new(&mut document, ml).unwrap();
+            mus.open("p").unwrap();
+            assert!(mus.self_closing("br").is_ok());
+            let err = mus.self_closing("div").unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<MarkupError>(),
+                Some(&MarkupError::NotVoidElement("div".to_string()))
+            );
+        }
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.open("p").unwrap();
+        assert!(mus.self_closing("div").is_ok());
+    }
+
+    #[test]
+    fn is_inside_nested_tables() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+
+        mus.open("body").unwrap();
+        assert!(!mus.is_inside("table"));
+        mus.open_many(&["table", "tr", "td", "table", "tr", "td"])
+            .unwrap();
+        assert!(mus.is_inside("table"));
+        assert_eq!(mus.ancestor_count("table"), 2);
+        assert_eq!(mus.ancestor_count("body"), 1);
+        mus.close_many(3).unwrap();
+        assert!(mus.is_inside("table"));
+        assert_eq!(mus.ancestor_count("table"), 1);
+        mus.close_many(3).unwrap();
+        assert!(!mus.is_inside("table"));
+        assert_eq!(mus.ancestor_count("table"), 0);
+    }
+
+    #[test]
+    fn attr_separator_modes() {
+        let write = |separator: AttrSeparator| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+            mus.set_formatter(Box::new(NoFormatting::new()));
+            mus.syntax.properties.as_mut().unwrap().value_separator = separator;
+            mus.open("div").unwrap();
+            properties!(mus, "id", "main", "class", "box").unwrap();
+            properties!(mus, "title", "Box").unwrap();
+            mus.close().unwrap();
+            mus.finalize().unwrap();
+            document
+        };
+
+        assert_eq!(
+            write(AttrSeparator::Inline(Insertion::Single(' '))),
+            concat![
+                "<!DOCTYPE html>\n",
+                r#"<div id="main" class="box" title="Box"></div>"#
+            ]
+        );
+        assert_eq!(
+            write(AttrSeparator::EachOnLine),
+            concat![
+                "<!DOCTYPE html>\n",
+                "<div id=\"main\"\n",
+                "     class=\"box\"\n",
+                "     title=\"Box\"></div>"
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_syntax_rejected_by_new() {
+        let mut cfg = SyntaxConfig::from(Language::Html);
+        cfg.properties = None;
+        let mut document = String::new();
+        assert!(MarkupSth::new(&mut document, Language::Other(cfg)).is_err());
+    }
+
+    #[test]
+    fn declarations_before_root() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.declaration(r#"ENTITY author "Jane Doe""#).unwrap();
+        mus.declaration(r#"ENTITY year "2024""#).unwrap();
+        mus.open("root").unwrap();
+        mus.text("Text").unwrap();
+        mus.close().unwrap();
+        assert_err!(mus.declaration("ENTITY late \"x\""));
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                "\n",
+                r#"<!ENTITY author "Jane Doe">"#,
+                "\n",
+                r#"<!ENTITY year "2024">"#,
+                "\n",
+                "<root>Text</root>"
+            ]
+        );
+    }
+
+    #[test]
+    fn buffer_reflects_written_tags() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        assert_eq!(mus.buffer(), Some(""));
+        mus.open("body").unwrap();
+        mus.open("div").unwrap();
+        assert_eq!(mus.buffer(), Some("<!DOCTYPE html>\n<body><div"));
+        mus.text("Text").unwrap();
+        assert_eq!(mus.buffer(), Some("<!DOCTYPE html>\n<body><div>Text"));
+
+        let mut writer = CountingWriter::default();
+        let mus = MarkupSth::from_writer(&mut writer, Language::Html).unwrap();
+        assert_eq!(mus.buffer(), None);
+    }
+
+    #[test]
+    fn len_of_string_and_writer() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        assert!(mus.is_empty());
+        mus.open("body").unwrap();
+        assert_eq!(mus.len(), "<!DOCTYPE html>\n<body".len());
+        mus.text("Grüße").unwrap();
+        assert_eq!(mus.len(), "<!DOCTYPE html>\n<body>Grüße".len());
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(document.len(), "<!DOCTYPE html>\n<body>Grüße</body>".len());
+
+        let mut document = String::from("<!-- head -->\n");
+        let mus = MarkupSth::append_to(&mut document, Language::Html).unwrap();
+        assert_eq!(mus.len(), 14);
+
+        let mut writer = String::new();
+        let mut mus = MarkupSth::from_writer(&mut writer, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        assert_eq!(mus.len(), 0);
+        mus.open("p").unwrap();
+        mus.text("a < b").unwrap();
+        assert_eq!(mus.len(), "<!DOCTYPE html>\n<p>a &lt; b".len());
+        mus.close().unwrap();
+        mus.new_line().unwrap();
+        assert_eq!(mus.len(), "<!DOCTYPE html>\n<p>a &lt; b</p>\n".len());
+        mus.finalize().unwrap();
+        assert_eq!(writer, "<!DOCTYPE html>\n<p>a &lt; b</p>\n");
+    }
+
+    #[test]
+    fn strict_close_on_finalize() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.strict_close = true;
+        mus.open("body").unwrap();
+        mus.open("p").unwrap();
+        mus.close_all().unwrap();
+        assert!(mus.finalize().is_ok());
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.strict_close = true;
+        mus.open("body").unwrap();
+        mus.open("p").unwrap();
+        let err = mus.finalize().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<MarkupError>(),
+            Some(MarkupError::UnclosedTags { remaining }) if remaining == &["body", "p"]
+        ));
+    }
+
+    #[test]
+    fn open_close_empty_without_linefeed() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+        mus.collapse_empty_pairs = true;
+        mus.open("body").unwrap();
+        mus.open_close_empty("div").unwrap();
+        mus.open_close_empty("span").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                "<body>\n",
+                "    <div></div>\n",
+                "    <span></span>\n",
+                "</body>"
+            ]
+        );
+    }
+
+    #[test]
+    fn indent_step_kept_when_swapping_formatter() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        let mut formatter = AlwaysIndentAlwaysLf::new();
+        formatter.set_indent_step_size(2);
+        mus.set_formatter(Box::new(formatter));
+        mus.open_many(&["body", "div"]).unwrap();
+        mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+        assert_eq!(mus.formatter.get_indent_step_size(), 2);
+        mus.open("p").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                "<body>\n",
+                "  <div>\n",
+                "    <p>\n",
+                "    </p>\n",
+                "  </div>\n",
+                "</body>"
+            ]
+        );
+    }
+
+    #[test]
+    fn auto_indent_state_synced_when_swapping_formatter() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+        mus.open_many(&["body", "div"]).unwrap();
+        mus.set_formatter(Box::new(AutoIndent::html()));
+        mus.open("p").unwrap();
+        mus.text("Text").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                "<body>\n",
+                "    <div>\n",
+                "        <p>Text</p>\n",
+                "    </div>\n",
+                "</body>"
+            ]
+        );
+
+        // Without any rules, the new formatter keeps the document's indenting.
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+        mus.open("body").unwrap();
+        mus.text("a").unwrap();
+        mus.set_formatter(Box::new(AutoIndent::new()));
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(document, "<!DOCTYPE html>\n<body>\n    a</body>");
+    }
+
+    #[test]
+    fn verbatim_block_keeps_indenting() {
+        let write = |verbatim_tag: Option<&str>| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+            mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+            mus.verbatim_tag = verbatim_tag.map(|t| t.to_string());
+            mus.open_many(&["body", "div"]).unwrap();
+            mus.verbatim_block("if a < b {\n    run();\n}").unwrap();
+            mus.close_all().unwrap();
+            mus.finalize().unwrap();
+            document
+        };
+
+        assert_eq!(
+            write(None),
+            concat![
+                "<!DOCTYPE html>\n",
+                "<body>\n",
+                "    <div>\n",
+                "        if a &lt; b {\n",
+                "            run();\n",
+                "        }\n",
+                "    </div>\n",
+                "</body>"
+            ]
+        );
+        assert_eq!(
+            write(Some("pre")),
+            concat![
+                "<!DOCTYPE html>\n",
+                "<body>\n",
+                "    <div>\n",
+                "        <pre>\n",
+                "            if a &lt; b {\n",
+                "                run();\n",
+                "            }\n",
+                "        </pre>\n",
+                "    </div>\n",
+                "</body>"
+            ]
+        );
+    }
+
+    #[test]
+    fn entities_by_name() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open("p").unwrap();
+        mus.entity("copy").unwrap();
+        mus.text(" 2024").unwrap();
+        mus.entity("nbsp").unwrap();
+        mus.entity("#160").unwrap();
+        mus.entity("#xA0").unwrap();
+        for name in ["", "nb sp", "amp;", "#", "#x", "#12a", "1st"] {
+            let err = mus.entity(name).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<MarkupError>(),
+                Some(MarkupError::InvalidEntityName(n)) if n == name
+            ));
+        }
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<p>&copy; 2024&nbsp;&#160;&#xA0;</p>"
+        );
+    }
+
+    #[test]
+    fn self_closing_slash_toggle() {
+        let write = |self_closing_slash: bool| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+            mus.set_formatter(Box::new(NoFormatting::new()));
+            mus.self_closing_slash = self_closing_slash;
+            mus.open("p").unwrap();
+            mus.self_closing("br").unwrap();
+            mus.self_closing("img").unwrap();
+            properties!(mus, "src", "a.png").unwrap();
+            mus.close().unwrap();
+            mus.finalize().unwrap();
+            document
+        };
+
+        assert_eq!(
+            write(false),
+            concat!["<!DOCTYPE html>\n", r#"<p><br><img src="a.png"></p>"#]
+        );
+        assert_eq!(
+            write(true),
+            concat!["<!DOCTYPE html>\n", r#"<p><br /><img src="a.png" /></p>"#]
+        );
+    }
+
+    fn sanitized(mode: SanitizeMode) -> Result<String> {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html)?;
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.allowed_tags = Some(["p", "b"].iter().map(|t| t.to_string()).collect());
+        mus.sanitize_mode = mode;
+        mus.open("p")?;
+        mus.open("script")?;
+        properties!(mus, "src", "evil.js")?;
+        mus.text("Text")?;
+        mus.open("b")?;
+        mus.text("bold")?;
+        mus.close()?;
+        mus.self_closing("img")?;
+        properties!(mus, "src", "a.png")?;
+        mus.close_all()?;
+        mus.finalize()?;
+        Ok(document)
+    }
+
+    #[test]
+    fn allowed_tags_sanitizing() {
+        assert_eq!(
+            sanitized(SanitizeMode::Skip).unwrap(),
+            "<!DOCTYPE html>\n<p>Text<b>bold</b></p>"
+        );
+        let err = sanitized(SanitizeMode::Error).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<MarkupError>(),
+            Some(MarkupError::DisallowedTag(tag)) if tag == "script"
+        ));
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.allowed_tags = Some(["p"].iter().map(|t| t.to_string()).collect());
+        mus.open("p").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(document, "<!DOCTYPE html>\n<p></p>");
+    }
+
+    #[test]
+    fn line_hook_transforms_completed_lines() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+        mus.set_line_hook(|line| line.to_uppercase());
+        mus.open("body").unwrap();
+        mus.open("p").unwrap();
+        mus.text("Text\nmore text").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE HTML>\n",
+                "<BODY>\n",
+                "    <P>\n",
+                "        TEXT\n",
+                "MORE TEXT\n",
+                "    </P>\n",
+                "</BODY>"
+            ]
+        );
+    }
+
+    #[test]
+    fn text_unchecked_writes_verbatim() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open("td").unwrap();
+        mus.text_unchecked("42").unwrap();
+        mus.text_unchecked(" <b>&</b>").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(document, "<!DOCTYPE html>\n<td>42 <b>&</b></td>");
+    }
+
+    #[test]
+    fn namespaces_in_declaration_order() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.doctype_linefeed = false;
+        mus.open("root").unwrap();
+        mus.declare_namespace("", "urn:default").unwrap();
+        mus.declare_namespace("xs", "http://www.w3.org/2001/XMLSchema")
+            .unwrap();
+        mus.declare_namespace("a", "urn:a").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<root xmlns="urn:default" xmlns:xs="http://www.w3.org/2001/XMLSchema" "#,
+                r#"xmlns:a="urn:a"></root>"#
+            ]
+        );
+    }
+
+    #[test]
+    fn self_closing_slash_after_properties() {
+        let write = |ml: Language| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::new(&mut document, ml).unwrap();
+            mus.set_formatter(Box::new(NoFormatting::new()));
+            mus.doctype_linefeed = false;
+            mus.open("root").unwrap();
+            mus.self_closing("empty").unwrap();
+            mus.self_closing("item").unwrap();
+            properties!(mus, "id", "1", "name", "first").unwrap();
+            mus.close().unwrap();
+            mus.finalize().unwrap();
+            document
+        };
+
+        assert_eq!(
+            write(Language::Xml),
+            concat![
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<root><empty /><item id="1" name="first" /></root>"#
+            ]
+        );
+
+        let mut cfg = SyntaxConfig::from(Language::Xml);
+        cfg.doctype = None;
+        cfg.self_closing.as_mut().unwrap().after = Insertion::Double('/', '>');
+        assert_eq!(
+            write(Language::Other(cfg)),
+            r#"<root><empty/><item id="1" name="first"/></root>"#
+        );
+    }
+
+    #[test]
+    fn zero_one_and_many_properties() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.self_closing("br").unwrap();
+        mus.properties(&[]).unwrap();
+        mus.self_closing("img").unwrap();
+        mus.properties(&[]).unwrap();
+        mus.properties(&[("src", "a.png")]).unwrap();
+        mus.self_closing("input").unwrap();
+        mus.properties(&[("a", "1"), ("b", "2"), ("c", "3")])
+            .unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                r#"<br><img src="a.png"><input a="1" b="2" c="3">"#
+            ]
+        );
+    }
+
+    #[test]
+    fn repeat_renders_list() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open("ul").unwrap();
+        mus.repeat(vec!["one", "two", "three"], |mus, item| {
+            mus.open("li")?;
+            mus.text(item)?;
+            mus.close()
+        })
+        .unwrap();
+        let mut seen = Vec::new();
+        let result = mus.repeat([1, 2, 3], |_, item| {
+            seen.push(item);
+            match item {
+                2 => Err("stop".into()),
+                _ => Ok(()),
+            }
+        });
+        assert!(result.is_err());
+        assert_eq!(seen, [1, 2]);
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                "<ul><li>one</li><li>two</li><li>three</li></ul>"
+            ]
+        );
+    }
+
+    fn write_card(mus: &mut MarkupSth) -> Result<()> {
+        element!(mus, "div", { "class" => "card", "id" => "first" }, {
+            element!(mus, "h2", {}, {
+                mus.text("Title")?;
+            });
+            element!(mus, "p", { "class" => "text" }, {
+                mus.text("hi")?;
+            });
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn nested_element_macros() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        write_card(&mut mus).unwrap();
+        assert!(mus.seq_state.tag_stack.is_empty());
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                r#"<div class="card" id="first"><h2>Title</h2><p class="text">hi</p></div>"#
+            ]
+        );
+    }
+
+    #[test]
+    fn create_file_writes_document() {
+        let dir = std::env::temp_dir().join(format!("markupsth-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("index.html");
+        std::fs::write(&path, "old content, which is longer than the new one").unwrap();
+
+        let mut mus = MarkupSth::create_file(&path, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        assert_eq!(mus.buffer(), None);
+        mus.open("p").unwrap();
+        mus.text("Straße").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, "<!DOCTYPE html>\n<p>Straße</p>");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn create_file_reports_io_error() {
+        // Writing into /dev/full always fails with ENOSPC, once the buffer gets flushed.
+        let mut mus = MarkupSth::create_file("/dev/full", Language::Html).unwrap();
+        mus.open("p").unwrap();
+        assert!(mus.text(&"x".repeat(64 * 1024)).is_err());
+        mus.close().unwrap();
+        let err = mus.finalize().unwrap_err();
+        let io_error = err.downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(io_error.raw_os_error(), Some(28));
+    }
+
+    #[test]
+    fn noop_formatter_fast_path_output() {
+        let write = |formatter: Box<dyn Formatter>| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+            mus.set_formatter(formatter);
+            mus.open_many(&["body", "div"]).unwrap();
+            properties!(mus, "class", "box").unwrap();
+            mus.text("Text").unwrap();
+            mus.self_closing("br").unwrap();
+            mus.new_line().unwrap();
+            mus.close_all().unwrap();
+            mus.finalize().unwrap();
+            document
+        };
+
+        assert!(NoFormatting::new().is_noop());
+        assert_eq!(
+            write(Box::new(NoFormatting::new())),
+            write(Box::new(crate::formatters::FnFormatter::from_fn(|_| {
+                FormatChanges::nothing()
+            })))
+        );
+    }
+
+    #[test]
+    fn custom_xml_declaration() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        let declaration = XmlDeclaration {
+            encoding: Some("ISO-8859-1".to_string()),
+            standalone: Some(false),
+            ..Default::default()
+        };
+        mus.set_xml_declaration(&declaration).unwrap();
+        mus.open("root").unwrap();
+        assert_err!(mus.set_xml_declaration(&XmlDeclaration::default()));
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                r#"<?xml version="1.0" encoding="ISO-8859-1" standalone="no"?>"#,
+                "\n<root></root>"
+            ]
+        );
+    }
+
+    #[test]
+    fn typography_in_text_only() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.typography = true;
+        mus.open("p").unwrap();
+        properties!(mus, "title", "a 'b' -- c").unwrap();
+        mus.text(r#"He said "stop" -- twice & loud."#).unwrap();
+        mus.close().unwrap();
+        mus.open("script").unwrap();
+        mus.text(r#"let a = "b"; a--;"#).unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                r#"<p title="a 'b' -- c">"#,
+                "He said &ldquo;stop&rdquo; &mdash; twice &amp; loud.</p>",
+                r#"<script>let a = "b"; a--;</script>"#
+            ]
+        );
+    }
+
+    #[test]
+    fn properties_map_in_order() {
+        let mut attrs = Attributes::new();
+        attrs.insert("src", "image.jpg");
+        attrs.insert("width", "100");
+        attrs.insert("alt", "Image");
+        attrs.insert("width", "200");
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.self_closing("img").unwrap();
+        mus.properties_map(&attrs).unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                r#"<img src="image.jpg" width="200" alt="Image">"#
+            ]
+        );
+    }
+
+    #[test]
+    fn conditional_comment_with_tags() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open("head").unwrap();
+        mus.conditional_comment("IE", |mus| {
+            mus.self_closing("link")?;
+            properties!(mus, "rel", "stylesheet", "href", "ie.css")
+        })
+        .unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n<head><!--[if IE]>",
+                r#"<link rel="stylesheet" href="ie.css">"#,
+                "<![endif]--></head>"
+            ]
+        );
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+        mus.open("head").unwrap();
+        mus.conditional_comment("lt IE 9", |mus| mus.self_closing("link"))
+            .unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<head>\n    <!--[if lt IE 9]><link>\n    <![endif]-->\n</head>"
+        );
+    }
+
+    fn write_sample(mus: &mut MarkupSth) {
+        mus.open("body").unwrap();
+        mus.open("div").unwrap();
+        mus.open("p").unwrap();
+        mus.text("Some ").unwrap();
+        mus.open_close_w("em", "text").unwrap();
+        mus.close_all().unwrap();
+    }
+
+    #[test]
+    fn pretty_and_compact_presets() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::pretty(&mut document, Language::Html).unwrap();
+        write_sample(&mut mus);
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n<body>\n    <div>\n        <p>Some <em>text</em></p>\n",
+                "    </div>\n</body>"
+            ]
+        );
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        write_sample(&mut mus);
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<body><div><p>Some <em>text</em></p></div></body>"
+        );
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::pretty(&mut document, Language::Xml).unwrap();
+        mus.open("root").unwrap();
+        mus.open_close_w("item", "Text").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            concat![
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                "\n<root>\n    <item>\n        Text\n    </item>\n</root>"
+            ]
+        );
+    }
+
+    #[test]
+    fn open_close_w_escaped_and_raw() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.open_close_w("p", "a < b &amp; c").unwrap();
+        mus.open_close_w_raw("p", "a &lt; b &amp; c").unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<p>a &lt; b &amp;amp; c</p><p>a &lt; b &amp; c</p>"
+        );
+    }
+
+    #[test]
+    fn attr_list_joins_non_empty_values() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.open("div").unwrap();
+        mus.attr_list("class", &["a", "", "b"], " ").unwrap();
+        mus.close().unwrap();
+        mus.open("div").unwrap();
+        mus.attr_list("class", &["", ""], " ").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat!["<!DOCTYPE html>\n", r#"<div class="a b"></div><div></div>"#]
+        );
+    }
+
+    #[test]
+    fn byte_order_mark() {
+        let dir = std::env::temp_dir().join(format!("markupsth-bom-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.xml");
+
+        let mut mus = MarkupSth::create_file(&path, Language::Xml).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.write_bom = true;
+        mus.open_close_w("root", "Text").unwrap();
+        mus.finalize().unwrap();
+
+        let written = std::fs::read(&path).unwrap();
+        assert_eq!(&written[..3], &[0xEF, 0xBB, 0xBF]);
+        assert!(written[3..].starts_with(b"<?xml"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.write_bom = true;
+        mus.open_close_w("p", "Text").unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(document, "\u{FEFF}<!DOCTYPE html>\n<p>Text</p>");
+    }
+
+    #[test]
+    fn default_formatter_per_language() {
+        let write = |ml: Language| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::new(&mut document, ml).unwrap();
+            let auto_indenting = mus.formatter.get_ext_auto_indenting().is_some();
+            mus.open("root").unwrap();
+            mus.open_close_w("a", "Text").unwrap();
+            mus.open("b").unwrap();
+            mus.close().unwrap();
+            mus.open("c").unwrap();
+            mus.open_close_w("d", "More").unwrap();
+            mus.self_closing("br").unwrap();
+            mus.close_all().unwrap();
+            mus.finalize().unwrap();
+            (auto_indenting, document)
+        };
+
+        assert_eq!(
+            write(Language::Html),
+            (
+                true,
+                "<!DOCTYPE html>\n<root><a>Text</a><b></b><c><d>More</d><br></c></root>"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            write(Language::Xml),
+            (
+                true,
+                concat![
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+                    "<root>\n",
+                    "    <a>Text</a>\n",
+                    "    <b></b>\n",
+                    "    <c>\n",
+                    "        <d>More</d>\n",
+                    "        <br />\n",
+                    "    </c>\n",
+                    "</root>"
+                ]
+                .to_string()
+            )
+        );
+        let mut cfg = SyntaxConfig::from(Language::Xml);
+        cfg.doctype = None;
+        assert_eq!(
+            write(Language::Other(cfg)),
+            (
+                false,
+                "<root><a>Text</a><b></b><c><d>More</d><br /></c></root>".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn close_to_ancestor() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.open_many(&["body", "section", "div", "section", "ul", "li"])
+            .unwrap();
+        mus.text("Item").unwrap();
+        mus.close_to("section").unwrap();
+        assert_eq!(mus.ancestor_count("section"), 1);
+        mus.close_to("section").unwrap();
+        assert_err!(mus.close_to("section"));
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n<body><section><div><section><ul><li>Item</li></ul></section>",
+                "</div></section></body>"
+            ]
+        );
+    }
+
+    #[test]
+    fn mixed_content_detection() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.warn_mixed_content = true;
+        mus.open("order").unwrap();
+        mus.open_close_w("id", "42").unwrap();
+        mus.text("  ").unwrap();
+        mus.open("item").unwrap();
+        mus.open_close_w("name", "Apple").unwrap();
+        mus.close().unwrap();
+        assert!(mus.mixed_content().is_empty());
+
+        mus.open("note").unwrap();
+        mus.text("Deliver to ").unwrap();
+        mus.open_close_w("b", "door").unwrap();
+        mus.text(" only").unwrap();
+        mus.self_closing("br").unwrap();
+        mus.close_all().unwrap();
+        assert_eq!(mus.mixed_content(), &["note".to_string()]);
+        mus.finalize().unwrap();
+    }
+
+    #[test]
+    fn mixed_content_after_empty_pair() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.warn_mixed_content = true;
+        mus.open_close_empty("a").unwrap();
+        mus.text("hi").unwrap();
+        mus.self_closing("br").unwrap();
+        mus.open("p").unwrap();
+        mus.open_close_empty("span").unwrap();
+        mus.text("Text").unwrap();
+        mus.close().unwrap();
+        assert_eq!(mus.mixed_content(), &["p".to_string()]);
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<a></a>hi<br><p><span></span>Text</p>"
+        );
+    }
+
+    #[test]
+    fn full_doctypes() {
+        let write = |root: &str, public_id: Option<&str>, system_id: Option<&str>| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::compact(&mut document, Language::XHtml).unwrap();
+            mus.set_full_doctype(root, public_id, system_id).unwrap();
+            mus.open_close_empty(root).unwrap();
+            assert_err!(mus.set_full_doctype(root, None, None));
+            mus.finalize().unwrap();
+            document
+        };
+
+        assert_eq!(
+            write(
+                "html",
+                Some("-//W3C//DTD XHTML 1.0 Strict//EN"),
+                Some("http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd")
+            ),
+            concat![
+                r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "#,
+                r#""http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd">"#,
+                "\n<html></html>"
+            ]
+        );
+        assert_eq!(
+            write("book", None, Some("docbook.dtd")),
+            "<!DOCTYPE book SYSTEM \"docbook.dtd\">\n<book></book>"
+        );
+        assert_eq!(write("html", None, None), "<!DOCTYPE html>\n<html></html>");
+    }
+
+    #[test]
+    fn maximum_depth() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.set_max_depth(3);
+        mus.open_many(&["div", "div", "div"]).unwrap();
+        let err = mus.open("div").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MarkupError>(),
+            Some(&MarkupError::MaxDepthExceeded(3))
+        );
+        mus.self_closing("br").unwrap();
+        mus.close().unwrap();
+        mus.open("p").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<div><div><div><br></div><p></p></div></div>"
+        );
+    }
+
+    #[test]
+    fn numeric_properties() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.self_closing("rect").unwrap();
+        mus.attr_num("x", 10).unwrap();
+        mus.attr_num("y", -2.5).unwrap();
+        mus.attr_num("width", 1.0f32 / 3.0).unwrap();
+        mus.attr_num("data-long", "1".repeat(80)).unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            format!(
+                r#"<!DOCTYPE html>
+<rect x="10" y="-2.5" width="0.33333334" data-long="{}">"#,
+                "1".repeat(80)
+            )
+        );
+    }
+
+    #[test]
+    fn text_fmt_macro() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        let name = "Tom & Jerry";
+        mus.open("p").unwrap();
+        text_fmt!(mus, "Hello {}, you have {} messages", name, 3).unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<p>Hello Tom &amp; Jerry, you have 3 messages</p>"
+        );
+    }
+
+    #[test]
+    fn single_root_in_xml() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Xml).unwrap();
+        mus.open("root").unwrap();
+        mus.open_close_w("item", "Text").unwrap();
+        mus.close().unwrap();
+        let err = mus.open("second").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MarkupError>(),
+            Some(&MarkupError::MultipleRoots("second".to_string()))
+        );
+        assert_err!(mus.self_closing("second"));
+        mus.single_root = false;
+        mus.self_closing("second").unwrap();
+        mus.finalize().unwrap();
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.open_close_w("p", "First").unwrap();
+        mus.open_close_w("p", "Second").unwrap();
+        mus.finalize().unwrap();
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Xml).unwrap();
+        mus.open_close_empty("a").unwrap();
+        let err = mus.open("b").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MarkupError>(),
+            Some(&MarkupError::MultipleRoots("b".to_string()))
+        );
+        assert_err!(mus.open_close_empty("b"));
+        mus.finalize().unwrap();
+        assert!(document.ends_with("<a></a>"));
+    }
+
+    #[test]
+    fn deferred_self_closing_or_pair() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        let fmtr = mus.formatter.get_ext_auto_indenting().unwrap();
+        fmtr.add_tags_to_rule(&["list"], AutoFmtRule::IndentAlways)
+            .unwrap();
+        fmtr.add_tags_to_rule(&["node"], AutoFmtRule::LfClosing)
+            .unwrap();
+        mus.open("list").unwrap();
+        mus.self_closing_or_pair("node").unwrap();
+        properties!(mus, "id", "1").unwrap();
+        mus.close().unwrap();
+        mus.self_closing_or_pair("node").unwrap();
+        mus.text("Text").unwrap();
+        mus.close().unwrap();
+        mus.self_closing_or_pair("node").unwrap();
+        mus.self_closing_or_pair("node").unwrap();
+        mus.close().unwrap();
+        mus.close().unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                "\n<list>\n",
+                r#"    <node id="1" />"#,
+                "\n    <node>Text</node>\n    <node><node />\n    </node>\n</list>"
+            ]
+        );
+
+        // Only void elements are closed by a self-closing tag in HTML.
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.open("p").unwrap();
+        mus.self_closing_or_pair("span").unwrap();
+        mus.close().unwrap();
+        mus.self_closing_or_pair("br").unwrap();
+        mus.close().unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(document, "<!DOCTYPE html>\n<p><span></span><br></p>");
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.syntax.self_closing = None;
+        assert_err!(mus.self_closing_or_pair("node"));
+    }
+
+    #[test]
+    #[cfg(feature = "digest")]
+    fn digest_of_documents() {
+        let write = |text: &str| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+            mus.open_close_w("p", text).unwrap();
+            mus.finalize_digest().unwrap()
+        };
+        assert_eq!(write("Text"), write("Text"));
+        assert_ne!(write("Text"), write("Test"));
+
+        // The hash of a continued document covers the existing content as well.
+        let mut document = String::from("<!DOCTYPE html>\n");
+        let mut mus = MarkupSth::append_to(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open_close_w("p", "Text").unwrap();
+        let digest = mus.finalize_digest().unwrap();
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open("p").unwrap();
+        mus.text("Text").unwrap();
+        assert_ne!(mus.digest(), digest);
+        mus.close().unwrap();
+        assert_eq!(mus.finalize_digest().unwrap(), digest);
+    }
+
+    #[test]
+    fn canonical_sorted_output() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Xml).unwrap();
+        mus.set_formatter(Box::new(Canonical::new()));
+        mus.sort_attributes = true;
+        mus.open("root").unwrap();
+        properties!(mus, "version", "1", "id", "r").unwrap();
+        mus.open("item").unwrap();
+        properties!(mus, "z", "3", "a", "1", "m", "2").unwrap();
+        mus.text("Text").unwrap();
+        mus.close().unwrap();
+        mus.open("empty").unwrap();
+        mus.close().unwrap();
+        mus.self_closing("node").unwrap();
+        properties!(mus, "b", "2", "a", "1").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                "\n",
+                r#"<root id="r" version="1">"#,
+                "\n",
+                r#"<item a="1" m="2" z="3">Text</item>"#,
+                "\n<empty></empty>\n",
+                r#"<node a="1" b="2" />"#,
+                "\n</root>"
+            ]
+        );
+    }
+
+    #[test]
+    fn base_indent_shifts_every_line() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+        mus.base_indent = 8;
+        mus.open("div").unwrap();
+        mus.open("p").unwrap();
+        mus.text("Text").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "        <!DOCTYPE html>\n",
+                "        <div>\n",
+                "            <p>\n",
+                "                Text\n",
+                "            </p>\n",
+                "        </div>"
+            ]
+        );
+    }
+
+    #[test]
+    fn html5_data_attributes() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html5).unwrap();
+        mus.open("div").unwrap();
+        mus.data_attr("id", "42").unwrap();
+        mus.data_attrs(&[("user-name", r#"Tom "T" <&> Jerry"#), ("role", "admin")])
+            .unwrap();
+        let err = mus.data_attr("bad key", "x").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MarkupError>(),
+            Some(&MarkupError::InvalidAttributeName(
+                "data-bad key".to_string()
+            ))
+        );
+        assert_err!(mus.data_attr("", "x"));
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                r#"<div data-id="42" data-user-name="Tom &quot;T&quot; &lt;&amp;> Jerry" "#,
+                r#"data-role="admin"></div>"#
+            ]
+        );
+
+        // Values are written like by `properties()`.
+        let write = |data: bool| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::compact(&mut document, Language::Html5).unwrap();
+            mus.open("div").unwrap();
+            if data {
+                mus.data_attr("note", r#"Tom "T" & Jerry"#).unwrap();
+            } else {
+                properties!(mus, "data-note", r#"Tom "T" & Jerry"#).unwrap();
+            }
+            mus.close().unwrap();
+            mus.finalize().unwrap();
+            document
+        };
+        assert_eq!(write(true), write(false));
+        assert!(write(true).contains(r#"data-note="Tom &quot;T&quot; &amp; Jerry""#));
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.open("div").unwrap();
+        mus.data_attr("x", "a\"b<&").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+        assert!(document.ends_with(r#"<div data-x="a&quot;b&lt;&amp;"></div>"#));
+    }
+
+    #[test]
+    fn custom_escape_tables() {
+        let mut cfg = SyntaxConfig::from(Language::Xml);
+        cfg.doctype = None;
+        cfg.text_escapes = vec![('|', "\\|".to_string()), ('*', "\\*".to_string())];
+        cfg.attr_escapes = vec![('"', "&quot;".to_string())];
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Other(cfg)).unwrap();
+        assert!(mus.escape_text);
+        mus.open("cell").unwrap();
+        properties!(mus, "title", r#"a "b""#).unwrap();
+        mus.text("a | *b* & c").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(
+            document,
+            r#"<cell title="a &quot;b&quot;">a \| \*b\* & c</cell>"#
+        );
+    }
+
+    #[test]
+    fn close_after_self_closing_misuse() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.self_closing("img").unwrap();
+        let err = mus.close().unwrap_err().to_string();
+        assert!(err.contains("'img' is a self-closing tag"), "{}", err);
+
+        mus.open("p").unwrap();
+        mus.close().unwrap();
+        let err = mus.close().unwrap_err().to_string();
+        assert_eq!(err, "MarkupSth: cannot close, no tag pair is open");
+        mus.finalize().unwrap();
+    }
+
+    #[test]
+    fn max_blank_lines_between_fragments() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(MaxBlankLines::wrap(
+            Box::new(AlwaysIndentAlwaysLf::new()),
+            0,
+        )));
+        // Each fragment ends with a linefeed, which would create blank lines otherwise.
+        for text in ["one", "two"] {
+            mus.open("p").unwrap();
+            mus.text(text).unwrap();
+            mus.close().unwrap();
+            mus.new_line().unwrap();
+        }
+        mus.self_closing("hr").unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<p>\n    one\n</p>\n<p>\n    two\n</p>\n<hr>"
+        );
+    }
+
+    #[test]
+    fn open_with_attributes() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open_with("div", &[("class", "x")]).unwrap();
+        mus.self_closing("br").unwrap();
+        mus.close().unwrap();
+        mus.open_with("p", &[]).unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<div class=\"x\"><br></div><p></p>"
+        );
+    }
+
+    #[test]
+    fn properties_sorted_from_hash_map() {
+        let names = ["title", "id", "class", "data-x", "alt", "lang"];
+        let mut results = Vec::new();
+        // Different insertion orders and capacities, each map with its own random state.
+        for n in 0..names.len() {
+            let mut attrs = HashMap::with_capacity(n * 8);
+            for name in names.iter().cycle().skip(n).take(names.len()) {
+                attrs.insert(name.to_string(), format!("v-{}", name));
+            }
+            let mut document = String::new();
+            let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+            mus.set_formatter(Box::new(NoFormatting::new()));
+            mus.self_closing("img").unwrap();
+            mus.properties_sorted(&attrs).unwrap();
+            mus.finalize().unwrap();
+            results.push(document);
+        }
+        assert_eq!(
+            results[0],
+            "<!DOCTYPE html>\n<img alt=\"v-alt\" class=\"v-class\" data-x=\"v-data-x\" \
+             id=\"v-id\" lang=\"v-lang\" title=\"v-title\">"
+        );
+        assert!(results.iter().all(|r| r == &results[0]));
+    }
+
+    #[test]
+    fn raw_attr_style() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open("div").unwrap();
+        mus.raw_attr("style=\"color: red; margin: 0 auto\"")
+            .unwrap();
+        mus.close().unwrap();
+        mus.self_closing("img").unwrap();
+        mus.properties(&[("src", "a.png")]).unwrap();
+        mus.raw_attr("srcset=\"a.png 1x, a@2x.png 2x\"").unwrap();
+        mus.text("!").unwrap();
+        assert_err!(mus.raw_attr("hidden"));
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<div style=\"color: red; margin: 0 auto\"></div>\
+             <img src=\"a.png\" srcset=\"a.png 1x, a@2x.png 2x\">!"
+        );
+    }
+
+    #[test]
+    fn raw_region_inline_code() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+        mus.open("div").unwrap();
+        mus.open("p").unwrap();
+        mus.text("Use ").unwrap();
+        mus.raw_region(|mus| {
+            mus.open("code")?;
+            mus.open_close_w("b", "let")?;
+            mus.text(" x = 1;")?;
+            mus.close()
+        })
+        .unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                "<div>\n",
+                "    <p>\n",
+                "        Use <code><b>let</b> x = 1;</code>\n",
+                "    </p>\n",
+                "</div>",
+            ]
+        );
+    }
+
+    #[test]
+    fn raw_region_keeps_auto_indent_state() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(HtmlBlockIndent::new()));
+        mus.open("body").unwrap();
+        mus.open("div").unwrap();
+        mus.raw_region(|mus| {
+            mus.open("ul")?;
+            mus.open_close_w("li", "One")?;
+            mus.close()
+        })
+        .unwrap();
+        mus.open_close_w("p", "Text").unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                "<body>\n",
+                "    <div><ul><li>One</li></ul>\n",
+                "        <p>Text</p>\n",
+                "    </div>\n",
+                "</body>",
+            ]
+        );
+    }
+
+    #[test]
+    fn trim_policy_of_text() {
+        let policies = [
+            (TrimPolicy::None, "<p>  padded \t</p>"),
+            (TrimPolicy::Trim, "<p>padded</p>"),
+            (TrimPolicy::TrimStart, "<p>padded \t</p>"),
+            (TrimPolicy::TrimEnd, "<p>  padded</p>"),
+        ];
+        for (policy, expected) in policies {
+            let mut document = String::new();
+            let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+            mus.doctype_linefeed = false;
+            mus.trim_policy = policy;
+            mus.open_close_w("p", "  padded \t").unwrap();
+            mus.finalize().unwrap();
+            assert_eq!(document, format!("<!DOCTYPE html>{}", expected));
+        }
+    }
+
+    #[test]
+    fn embed_fragment_indented() {
+        let mut fragment = String::new();
+        let mut sub = MarkupSth::new(&mut fragment, Language::Html).unwrap();
+        sub.syntax.doctype = None;
+        sub.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+        sub.open("ul").unwrap();
+        sub.open_close_w("li", "A & B").unwrap();
+        sub.close().unwrap();
+        sub.finalize().unwrap();
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(AlwaysIndentAlwaysLf::new()));
+        mus.open("body").unwrap();
+        mus.open("div").unwrap();
+        mus.embed(&fragment).unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                "<body>\n",
+                "    <div>\n",
+                "        <ul>\n",
+                "            <li>\n",
+                "                A &amp; B\n",
+                "            </li>\n",
+                "        </ul>\n",
+                "    </div>\n",
+                "</body>",
+            ]
+        );
+    }
+
+    #[test]
+    fn properties_empty_value() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.self_closing("input").unwrap();
+        properties!(mus, "value", "").unwrap();
+        mus.self_closing("input").unwrap();
+        properties!(mus, "name", "q", "value", "", "id", "").unwrap();
+        mus.attr_quote_preference = AttrQuotes::Prefer('\'');
+        mus.self_closing("input").unwrap();
+        properties!(mus, "value", "").unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                r#"<input value="">"#,
+                r#"<input name="q" value="" id="">"#,
+                "<input value=''>",
+            ]
+        );
+    }
+
+    #[test]
+    fn reset_starts_clean() {
+        let generate = |mus: &mut MarkupSth| {
+            mus.open("body").unwrap();
+            mus.open("div").unwrap();
+            mus.open_close_w("p", "Text").unwrap();
+            mus.close_all().unwrap();
+        };
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(AutoIndent::html()));
+        // The first document is left with open tags and deep indenting.
+        mus.open_many(&["body", "section", "div"]).unwrap();
+        mus.open("p").unwrap();
+        mus.text("Discarded").unwrap();
+        mus.reset().unwrap();
+        assert_eq!(mus.buffer(), Some(""));
+        assert_eq!(mus.stats(), MarkupStats::default());
+        generate(&mut mus);
+        let second = mus.finish().unwrap();
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(AutoIndent::html()));
+        generate(&mut mus);
+        assert_eq!(second, mus.finish().unwrap());
+        assert!(second.starts_with("<!DOCTYPE html>\n<body>\n    <div>"));
+
+        let mut writer = String::new();
+        let mut mus = MarkupSth::from_writer(&mut writer, Language::Html).unwrap();
+        assert_err!(mus.reset());
+    }
+
+    #[test]
+    fn reset_keeps_appended_prefix() {
+        let mut document = String::from("<!DOCTYPE html>\n<body>\n");
+        let mut mus = MarkupSth::append_to(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.open_close_w("p", "Discarded").unwrap();
+        mus.reset().unwrap();
+        assert_eq!(mus.buffer(), Some("<!DOCTYPE html>\n<body>\n"));
+        assert_eq!(mus.len(), "<!DOCTYPE html>\n<body>\n".len());
+        mus.open_close_w("p", "Kept").unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(document, "<!DOCTYPE html>\n<body>\n<p>Kept</p>");
+    }
+
+    #[test]
+    fn ascii_only_references() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.ascii_only = true;
+        mus.open("p").unwrap();
+        properties!(mus, "title", "Café 😀").unwrap();
+        mus.text("Grüße 👋 & more").unwrap();
+        mus.close().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<p title=\"Caf&#xE9; &#x1F600;\">Gr&#xFC;&#xDF;e &#x1F44B; &amp; more</p>"
+        );
+    }
+
+    #[test]
+    fn close_current_if_matching() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        assert!(!mus.close_current_if("div").unwrap());
+        mus.open("div").unwrap();
+        mus.open("span").unwrap();
+        assert!(!mus.close_current_if("div").unwrap());
+        assert!(mus.close_current_if("span").unwrap());
+        assert!(mus.close_current_if("div").unwrap());
+        assert!(!mus.close_current_if("div").unwrap());
+        mus.finalize().unwrap();
+        assert_eq!(document, "<!DOCTYPE html>\n<div><span></span></div>");
+    }
+
+    #[test]
+    fn auto_cdata_threshold_boundary() {
+        let write = |ml: Language, texts: &[&str]| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::compact(&mut document, ml).unwrap();
+            mus.doctype_linefeed = false;
+            mus.auto_cdata_threshold = Some(2);
+            mus.open("code").unwrap();
+            for text in texts {
+                mus.text(text).unwrap();
+            }
+            mus.close().unwrap();
+            mus.finalize().unwrap();
+            document
+        };
+
+        // Two characters to escape are escaped, three are wrapped.
+        let document = write(Language::Xml, &["a < b & c", "a < b && c"]);
+        assert!(document.ends_with("<code>a &lt; b &amp; c<![CDATA[a < b && c]]></code>"));
+        let document = write(Language::Xml, &["x]]>y <&>"]);
+        assert!(document.ends_with("<code><![CDATA[x]]]]><![CDATA[>y <&>]]></code>"));
+        // Only in XML.
+        let document = write(Language::Html, &["<&>"]);
+        assert!(document.ends_with("<code>&lt;&amp;&gt;</code>"));
+    }
+
+    #[test]
+    fn attr_points_polyline() {
+        let points = [(0.0, 0.0), (10.5, 20.0), (1.0 / 3.0, 0.1 + 0.2)];
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Xml).unwrap();
+        mus.doctype_linefeed = false;
+        mus.self_closing("polyline").unwrap();
+        mus.attr_points("points", &points).unwrap();
+        mus.finalize().unwrap();
+        assert!(document.ends_with(
+            r#"<polyline points="0,0 10.5,20 0.3333333333333333,0.30000000000000004" />"#
+        ));
+
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Xml).unwrap();
+        mus.float_precision = Some(2);
+        mus.self_closing("polyline").unwrap();
+        mus.attr_points("points", &points).unwrap();
+        mus.finalize().unwrap();
+        assert!(document.ends_with(r#"<polyline points="0,0 10.5,20 0.33,0.3" />"#));
+    }
+
+    #[test]
+    fn float_precision_of_helpers() {
+        let write = |precision: Option<usize>| {
+            let mut document = String::new();
+            let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+            mus.doctype_linefeed = false;
+            mus.float_precision = precision;
+            mus.self_closing("svg").unwrap();
+            mus.attr_float("a", 0.1 + 0.2).unwrap();
+            mus.attr_float("b", 1.0 / 3.0).unwrap();
+            mus.attr_float("c", 10.0).unwrap();
+            mus.attr_float("d", -0.0000001).unwrap();
+            mus.attr_points("points", &[(2.5, 1.0 / 7.0)]).unwrap();
+            mus.finalize().unwrap();
+            document
+        };
+        assert_eq!(
+            write(None),
+            "<!DOCTYPE html><svg a=\"0.30000000000000004\" b=\"0.3333333333333333\" c=\"10\" \
+             d=\"-0.0000001\" points=\"2.5,0.14285714285714285\">"
+        );
+        assert_eq!(
+            write(Some(2)),
+            "<!DOCTYPE html><svg a=\"0.3\" b=\"0.33\" c=\"10\" d=\"0\" points=\"2.5,0.14\">"
+        );
+        assert_eq!(
+            write(Some(6)),
+            "<!DOCTYPE html><svg a=\"0.3\" b=\"0.333333\" c=\"10\" d=\"0\" \
+             points=\"2.5,0.142857\">"
+        );
+    }
+
+    #[test]
+    fn open_tag_typed() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::compact(&mut document, Language::Html).unwrap();
+        mus.open_tag(HtmlTag::Body).unwrap();
+        mus.open_tag(HtmlTag::Section).unwrap();
+        mus.open_tag(HtmlTag::H1).unwrap();
+        mus.text("Title").unwrap();
+        mus.close().unwrap();
+        mus.open_tag(HtmlTag::Img).unwrap();
+        properties!(mus, "src", "a.png").unwrap();
+        mus.open_tag(HtmlTag::Br).unwrap();
+        mus.open_tag(HtmlTag::P).unwrap();
+        mus.close_all().unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            "<!DOCTYPE html>\n<body><section><h1>Title</h1><img src=\"a.png\"><br><p></p></section></body>"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! This module contains a simple tree definition of a Markup document, the `Node`. It bridges
+//! DOM-like builders and the streaming API of `MarkupSth`, so a whole tree can be written at once
+//! by using `MarkupSth::write_node()`.
+//!
+//! ### Example
+//!
+//! ```
+//! use markupsth::{Language, MarkupSth, NoFormatting, Formatter, Node};
+//!
+//! let tree = Node::Element {
+//!     name: "p".to_string(),
+//!     attrs: vec![("class".to_string(), "note".to_string())],
+//!     children: vec![Node::Text("Hello".to_string())],
+//! };
+//!
+//! let mut document = String::new();
+//! let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+//! mus.set_formatter(Box::new(NoFormatting::new()));
+//! mus.write_node(&tree).unwrap();
+//! mus.finalize().unwrap();
+//! assert_eq!(document, concat!["<!DOCTYPE html>\n", r#"<p class="note">Hello</p>"#]);
+//! ```
+
+/// A single node of a simple Markup document tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    /// A tag pair element with properties and child nodes, e.g. `<div id="a">...</div>`.
+    Element {
+        /// Name of the tag.
+        name: String,
+        /// Properties of the tag as (name, value) pairs, in the order to be written.
+        attrs: Vec<(String, String)>,
+        /// Child nodes between opening and closing tag.
+        children: Vec<Node>,
+    },
+    /// Regular text content.
+    Text(String),
+    /// A self-closing tag element with properties, e.g. `<img src="image.jpg">`.
+    SelfClosing {
+        /// Name of the tag.
+        name: String,
+        /// Properties of the tag as (name, value) pairs, in the order to be written.
+        attrs: Vec<(String, String)>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AutoFmtRule, Language, MarkupSth};
+
+    fn element(name: &str, attrs: &[(&str, &str)], children: Vec<Node>) -> Node {
+        Node::Element {
+            name: name.to_string(),
+            attrs: attrs
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            children,
+        }
+    }
+
+    fn tree() -> Node {
+        element(
+            "section",
+            &[("id", "main")],
+            vec![
+                Node::SelfClosing {
+                    name: "img".to_string(),
+                    attrs: vec![("src".to_string(), "image.jpg".to_string())],
+                },
+                element("p", &[], vec![Node::Text("This is Text".to_string())]),
+                element("div", &[], vec![]),
+            ],
+        )
+    }
+
+    fn write_tree(ml: Language) -> String {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, ml).unwrap();
+        let fmtr = mus.formatter.get_ext_auto_indenting().unwrap();
+        fmtr.add_tags_to_rule(&["section"], AutoFmtRule::IndentAlways)
+            .unwrap();
+        fmtr.add_tags_to_rule(&["img", "p", "div"], AutoFmtRule::LfClosing)
+            .unwrap();
+        mus.write_node(&tree()).unwrap();
+        mus.finalize().unwrap();
+        document
+    }
+
+    #[test]
+    fn write_node_html() {
+        assert_eq!(
+            write_tree(Language::Html),
+            concat![
+                "<!DOCTYPE html>\n",
+                "<section id=\"main\">\n",
+                "    <img src=\"image.jpg\">\n",
+                "    <p>This is Text</p>\n",
+                "    <div></div>\n",
+                "</section>"
+            ]
+        );
+    }
+
+    #[test]
+    fn write_node_xml() {
+        assert_eq!(
+            write_tree(Language::Xml),
+            concat![
+                "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+                "<section id=\"main\">\n",
+                "    <img src=\"image.jpg\" />\n",
+                "    <p>This is Text</p>\n",
+                "    <div></div>\n",
+                "</section>"
+            ]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! This module contains the trait `Markup`, which lets custom types render themselves into a
+//! `MarkupSth`, similar to `std::fmt::Display`. Types implementing it can be inserted by
+//! `MarkupSth::render()` and composed of each other.
+//!
+//! ### Example
+//!
+//! ```
+//! use markupsth::{Formatter, Language, Markup, MarkupSth, NoFormatting, Result};
+//!
+//! struct Badge<'a>(&'a str);
+//!
+//! impl Markup for Badge<'_> {
+//!     fn render(&self, mus: &mut MarkupSth) -> Result<()> {
+//!         mus.open_with("span", &[("class", "badge")])?;
+//!         mus.text(self.0)?;
+//!         mus.close()
+//!     }
+//! }
+//!
+//! let mut document = String::new();
+//! let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+//! mus.set_formatter(Box::new(NoFormatting::new()));
+//! mus.render(&Badge("new")).unwrap();
+//! mus.finalize().unwrap();
+//! assert_eq!(document, "<!DOCTYPE html>\n<span class=\"badge\">new</span>");
+//! ```
+
+use crate::{MarkupSth, Node, Result};
+
+/// A type, which can render itself into a `MarkupSth`.
+pub trait Markup {
+    /// Renders this value into `mus`.
+    fn render(&self, mus: &mut MarkupSth) -> Result<()>;
+}
+
+impl Markup for Node {
+    fn render(&self, mus: &mut MarkupSth) -> Result<()> {
+        mus.write_node(self)
+    }
+}
+
+impl<T: Markup> Markup for [T] {
+    fn render(&self, mus: &mut MarkupSth) -> Result<()> {
+        for value in self.iter() {
+            value.render(mus)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Markup> Markup for Option<T> {
+    fn render(&self, mus: &mut MarkupSth) -> Result<()> {
+        match self {
+            Some(value) => value.render(mus),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Formatter, Language, NoFormatting};
+
+    struct Card {
+        title: String,
+        tags: Vec<Tag>,
+        footer: Option<Tag>,
+    }
+
+    struct Tag(&'static str);
+
+    impl Markup for Tag {
+        fn render(&self, mus: &mut MarkupSth) -> Result<()> {
+            mus.open_close_w("li", self.0)
+        }
+    }
+
+    impl Markup for Card {
+        fn render(&self, mus: &mut MarkupSth) -> Result<()> {
+            mus.open_with("div", &[("class", "card")])?;
+            mus.open_close_w("h2", &self.title)?;
+            mus.open("ul")?;
+            mus.render(self.tags.as_slice())?;
+            mus.render(&self.footer)?;
+            mus.close()?;
+            mus.render(&Node::SelfClosing {
+                name: "hr".to_string(),
+                attrs: Vec::new(),
+            })?;
+            mus.close()
+        }
+    }
+
+    #[test]
+    fn render_custom_struct() {
+        let card = Card {
+            title: "Fish & Chips".to_string(),
+            tags: vec![Tag("fried"), Tag("salty")],
+            footer: None,
+        };
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(NoFormatting::new()));
+        mus.render(&card).unwrap();
+        mus.finalize().unwrap();
+        assert_eq!(
+            document,
+            concat![
+                "<!DOCTYPE html>\n",
+                r#"<div class="card"><h2>Fish &amp; Chips</h2>"#,
+                "<ul><li>fried</li><li>salty</li></ul><hr></div>",
+            ]
+        );
+    }
+}
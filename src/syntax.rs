@@ -50,21 +50,27 @@
 //!            closing_after: Single('|'),
 //!        }),
 //!        properties: None,
+//!        text_escapes: Vec::new(),
+//!        attr_escapes: Vec::new(),
 //!    };
 //!
 //!    let mut document = String::new();
 //!    let mut markupsth = MarkupSth::new(&mut document, Language::Other(cfg)).unwrap();
 //!    ```
 
-use std::fmt;
+use crate::{
+    format::{Sequence, TagSequence},
+    Result,
+};
+use std::{borrow::Cow, fmt};
 use Insertion::*;
 
 /// Defines an auto-insertion of MarkupSth before/after a tag element in form of (a) character(s).
 ///
-/// Possible types auf automatic insertions, respectively no insertion or 1-3 characters. For
-/// example in HTML and XML, every tag will be openend by a single character `<` and closed by
-/// either a single character `>` or maybe by two `/>`. This different setups can be defined this
-/// enumeration type. Note: this is the definition of one insertion either before or after a tag
+/// Possible types auf automatic insertions, respectively no insertion, 1-3 characters or a longer
+/// string. For example in HTML and XML, every tag will be openend by a single character `<` and
+/// closed by either a single character `>` or maybe by two `/>`. This different setups can be
+/// defined this enumeration type. Note: this is the definition of one insertion either before or after a tag
 /// identifier.
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Insertion {
@@ -76,6 +82,8 @@ pub enum Insertion {
     Double(char, char),
     /// Three characters.
     Triple(char, char, char),
+    /// Any longer string, e.g. `\begin{` in LaTeX.
+    Str(&'static str),
 }
 
 impl fmt::Display for Insertion {
@@ -85,6 +93,7 @@ impl fmt::Display for Insertion {
             Single(c) => write!(f, "{}", c),
             Double(c1, c2) => write!(f, "{}{}", c1, c2),
             Triple(c1, c2, c3) => write!(f, "{}{}{}", c1, c2, c3),
+            Str(s) => f.write_str(s),
         }
     }
 }
@@ -97,7 +106,9 @@ impl fmt::Display for Insertion {
 pub struct SelfClosingTagConfig {
     /// Optional character to be set before a single tag name (opening character).
     pub before: Insertion,
-    /// Optional character to be set after a single tag (closing character).
+    /// Optional character to be set after a single tag (closing character). It is inserted after
+    /// all properties of the tag, so a slash form like `Double('/', '>')` results in
+    /// `<tag attr="v"/>`.
     pub after: Insertion,
 }
 
@@ -134,7 +145,16 @@ pub struct PropertyConfig {
     /// Seperator between property name and property value.
     pub name_separator: Insertion,
     /// Separator between multiple properties.
-    pub value_separator: Insertion,
+    pub value_separator: AttrSeparator,
+}
+
+/// Defines the separator between multiple properties of a tag element.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AttrSeparator {
+    /// All properties are placed on the same line, separated by an insertion.
+    Inline(Insertion),
+    /// Each property is placed on its own line, aligned to the first property of the tag.
+    EachOnLine,
 }
 
 /// Defines a full configuration of a complete syntax in this crate, such as HTML or XML.
@@ -151,23 +171,498 @@ pub struct SyntaxConfig {
     /// Configuration of properties of tag elements. When set to `None`, it means there are no tag
     /// properties available in the Markup language.
     pub properties: Option<PropertyConfig>,
+    /// Replacements of characters in text content, e.g. `&` by `&amp;` in HTML. Applied by
+    /// `MarkupSth::text()`, if `MarkupSth::escape_text` is set.
+    pub text_escapes: Vec<(char, String)>,
+    /// Replacements of characters in property values, applied by `MarkupSth::properties()`, e.g.
+    /// `&` by `&amp;` and `<` by `&lt;` in HTML and XML. Quotes are handled by
+    /// `MarkupSth::attr_quote_preference`.
+    pub attr_escapes: Vec<(char, String)>,
+}
+
+/// The XML declaration, which is the doctype of XML documents, e.g.
+/// `<?xml version="1.0" encoding="UTF-8" standalone="yes"?>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XmlDeclaration {
+    /// The XML version, e.g. `1.0`.
+    pub version: String,
+    /// Optional encoding, e.g. `UTF-8` or `ISO-8859-1`.
+    pub encoding: Option<String>,
+    /// Optional standalone declaration, `yes` or `no`.
+    pub standalone: Option<bool>,
+}
+
+impl Default for XmlDeclaration {
+    /// The declaration used by `Language::Xml`.
+    fn default() -> XmlDeclaration {
+        XmlDeclaration {
+            version: "1.0".to_string(),
+            encoding: Some("UTF-8".to_string()),
+            standalone: Some(true),
+        }
+    }
+}
+
+impl fmt::Display for XmlDeclaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, r#"<?xml version="{}""#, self.version)?;
+        if let Some(encoding) = &self.encoding {
+            write!(f, r#" encoding="{}""#, encoding)?;
+        }
+        if let Some(standalone) = self.standalone {
+            let standalone = if standalone { "yes" } else { "no" };
+            write!(f, r#" standalone="{}""#, standalone)?;
+        }
+        f.write_str("?>")
+    }
+}
+
+/// Void elements of HTML, which never have any content and are written as self-closing tags.
+pub const HTML_VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Common tags of HTML, as a typed alternative to tag names as strings, see
+/// `MarkupSth::open_tag()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HtmlTag {
+    /// `<a>`
+    A,
+    /// `<abbr>`
+    Abbr,
+    /// `<area>`
+    Area,
+    /// `<article>`
+    Article,
+    /// `<aside>`
+    Aside,
+    /// `<b>`
+    B,
+    /// `<base>`
+    Base,
+    /// `<blockquote>`
+    Blockquote,
+    /// `<body>`
+    Body,
+    /// `<br>`
+    Br,
+    /// `<button>`
+    Button,
+    /// `<code>`
+    Code,
+    /// `<col>`
+    Col,
+    /// `<div>`
+    Div,
+    /// `<em>`
+    Em,
+    /// `<embed>`
+    Embed,
+    /// `<footer>`
+    Footer,
+    /// `<form>`
+    Form,
+    /// `<h1>`
+    H1,
+    /// `<h2>`
+    H2,
+    /// `<h3>`
+    H3,
+    /// `<h4>`
+    H4,
+    /// `<h5>`
+    H5,
+    /// `<h6>`
+    H6,
+    /// `<head>`
+    Head,
+    /// `<header>`
+    Header,
+    /// `<hr>`
+    Hr,
+    /// `<html>`
+    Html,
+    /// `<i>`
+    I,
+    /// `<img>`
+    Img,
+    /// `<input>`
+    Input,
+    /// `<label>`
+    Label,
+    /// `<li>`
+    Li,
+    /// `<link>`
+    Link,
+    /// `<main>`
+    Main,
+    /// `<meta>`
+    Meta,
+    /// `<nav>`
+    Nav,
+    /// `<ol>`
+    Ol,
+    /// `<option>`
+    Option,
+    /// `<p>`
+    P,
+    /// `<param>`
+    Param,
+    /// `<pre>`
+    Pre,
+    /// `<script>`
+    Script,
+    /// `<section>`
+    Section,
+    /// `<select>`
+    Select,
+    /// `<source>`
+    Source,
+    /// `<span>`
+    Span,
+    /// `<strong>`
+    Strong,
+    /// `<style>`
+    Style,
+    /// `<table>`
+    Table,
+    /// `<tbody>`
+    Tbody,
+    /// `<td>`
+    Td,
+    /// `<textarea>`
+    Textarea,
+    /// `<th>`
+    Th,
+    /// `<thead>`
+    Thead,
+    /// `<title>`
+    Title,
+    /// `<tr>`
+    Tr,
+    /// `<track>`
+    Track,
+    /// `<ul>`
+    Ul,
+    /// `<wbr>`
+    Wbr,
+}
+
+impl HtmlTag {
+    /// Returns the name of the tag.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HtmlTag::A => "a",
+            HtmlTag::Abbr => "abbr",
+            HtmlTag::Area => "area",
+            HtmlTag::Article => "article",
+            HtmlTag::Aside => "aside",
+            HtmlTag::B => "b",
+            HtmlTag::Base => "base",
+            HtmlTag::Blockquote => "blockquote",
+            HtmlTag::Body => "body",
+            HtmlTag::Br => "br",
+            HtmlTag::Button => "button",
+            HtmlTag::Code => "code",
+            HtmlTag::Col => "col",
+            HtmlTag::Div => "div",
+            HtmlTag::Em => "em",
+            HtmlTag::Embed => "embed",
+            HtmlTag::Footer => "footer",
+            HtmlTag::Form => "form",
+            HtmlTag::H1 => "h1",
+            HtmlTag::H2 => "h2",
+            HtmlTag::H3 => "h3",
+            HtmlTag::H4 => "h4",
+            HtmlTag::H5 => "h5",
+            HtmlTag::H6 => "h6",
+            HtmlTag::Head => "head",
+            HtmlTag::Header => "header",
+            HtmlTag::Hr => "hr",
+            HtmlTag::Html => "html",
+            HtmlTag::I => "i",
+            HtmlTag::Img => "img",
+            HtmlTag::Input => "input",
+            HtmlTag::Label => "label",
+            HtmlTag::Li => "li",
+            HtmlTag::Link => "link",
+            HtmlTag::Main => "main",
+            HtmlTag::Meta => "meta",
+            HtmlTag::Nav => "nav",
+            HtmlTag::Ol => "ol",
+            HtmlTag::Option => "option",
+            HtmlTag::P => "p",
+            HtmlTag::Param => "param",
+            HtmlTag::Pre => "pre",
+            HtmlTag::Script => "script",
+            HtmlTag::Section => "section",
+            HtmlTag::Select => "select",
+            HtmlTag::Source => "source",
+            HtmlTag::Span => "span",
+            HtmlTag::Strong => "strong",
+            HtmlTag::Style => "style",
+            HtmlTag::Table => "table",
+            HtmlTag::Tbody => "tbody",
+            HtmlTag::Td => "td",
+            HtmlTag::Textarea => "textarea",
+            HtmlTag::Th => "th",
+            HtmlTag::Thead => "thead",
+            HtmlTag::Title => "title",
+            HtmlTag::Tr => "tr",
+            HtmlTag::Track => "track",
+            HtmlTag::Ul => "ul",
+            HtmlTag::Wbr => "wbr",
+        }
+    }
+
+    /// Returns whether the tag is a void element (see `HTML_VOID_ELEMENTS`), which is written as
+    /// self-closing tag.
+    pub fn is_void(&self) -> bool {
+        HTML_VOID_ELEMENTS.contains(&self.as_str())
+    }
+}
+
+impl fmt::Display for HtmlTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 /// Selector for available pre-defined syntax configurations and wrapper to pass your own.
+// A `Language` is only passed once on construction, so its size does not matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug)]
 pub enum Language {
     /// Selects the pre-defined HTML syntax.
     Html,
+    /// Selects the pre-defined HTML5 syntax, like `Html`, but only void elements (see
+    /// `HTML_VOID_ELEMENTS`) can be self-closing tags, see `MarkupSth::strict_void_elements`.
+    Html5,
+    /// Selects the pre-defined XHTML 1.0 (strict) syntax with self-closing tags like `<br />`.
+    /// Like in `Html5`, only void elements can be self-closing tags.
+    XHtml,
     /// Selects the pre-defined XML syntax.
     Xml,
     /// Wrapper selector to pass your own configuration.
     Other(SyntaxConfig),
 }
 
+/// Internal definition of the text escapes of HTML and XML, see `escape_text`.
+fn markup_text_escapes() -> Vec<(char, String)> {
+    vec![
+        ('&', "&amp;".to_string()),
+        ('<', "&lt;".to_string()),
+        ('>', "&gt;".to_string()),
+    ]
+}
+
+/// Internal definition of the property value escapes of HTML and XML. The quote character is
+/// escaped by `AttrQuotes::quote()`, because it depends on the quotes in use.
+fn markup_attr_escapes() -> Vec<(char, String)> {
+    vec![('&', "&amp;".to_string()), ('<', "&lt;".to_string())]
+}
+
+/// Replaces all characters of `text`, which are listed in the escape table `escapes`, e.g.
+/// `SyntaxConfig::text_escapes`.
+pub fn escape_with<'t>(text: &'t str, escapes: &[(char, String)]) -> Cow<'t, str> {
+    if !text.contains(|c| escapes.iter().any(|(e, _)| *e == c)) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match escapes.iter().find(|(e, _)| *e == c) {
+            Some((_, replacement)) => escaped.push_str(replacement),
+            None => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Escapes the characters `&`, `<` and `>` in text content of HTML and XML documents, see
+/// `escape_with()`.
+pub fn escape_text(text: &str) -> Cow<'_, str> {
+    escape_with(text, &markup_text_escapes())
+}
+
+/// Replaces all non-ASCII characters by numeric character references, e.g. `é` by `&#xE9;` or an
+/// emoji beyond the basic multilingual plane by `&#x1F600;`.
+pub fn escape_non_ascii(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len() + 16);
+    for c in text.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            escaped.push_str(&format!("&#x{:X};", c as u32));
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Substitutes typographic entities in text content: Straight double quotes become `&ldquo;` or
+/// `&rdquo;`, depending on whether they open or close a quotation, and `--` becomes `&mdash;`. A
+/// quote opens a quotation at the beginning of the text, after a whitespace or after an opening
+/// bracket, otherwise it closes one.
+pub fn typography(text: &str) -> Cow<'_, str> {
+    if !text.contains('"') && !text.contains("--") {
+        return Cow::Borrowed(text);
+    }
+    let mut result = String::with_capacity(text.len() + 16);
+    let mut prev: Option<char> = None;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => match prev {
+                None => result.push_str("&ldquo;"),
+                Some(p) if p.is_whitespace() || matches!(p, '(' | '[' | '{') => {
+                    result.push_str("&ldquo;")
+                }
+                Some(_) => result.push_str("&rdquo;"),
+            },
+            '-' if chars.peek() == Some(&'-') => {
+                chars.next();
+                result.push_str("&mdash;");
+            }
+            _ => result.push(c),
+        }
+        prev = Some(c);
+    }
+    Cow::Owned(result)
+}
+
+/// Checks, whether `name` is a valid name of an entity reference `&name;`, either a named one like
+/// `nbsp` or a numeric one like `#160` or `#xA0`.
+pub fn is_valid_entity_name(name: &str) -> bool {
+    if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+        !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit())
+    } else if let Some(dec) = name.strip_prefix('#') {
+        !dec.is_empty() && dec.chars().all(|c| c.is_ascii_digit())
+    } else {
+        let mut chars = name.chars();
+        matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric())
+    }
+}
+
+/// Selector for the quotes around property values, see `MarkupSth::attr_quote_preference`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AttrQuotes {
+    /// Use the insertions of `PropertyConfig` as they are configured. If they quote by `"` or `'`,
+    /// this character will be escaped within the value (`&quot;` or `&#39;`).
+    Config,
+    /// Quote values by the preferred character (e.g. `"`), but switch to the other one of `"` and
+    /// `'`, if the value contains only the preferred one. If the value contains both, the
+    /// preferred quote will be kept and escaped within the value (`&quot;` or `&#39;`).
+    Prefer(char),
+}
+
+impl AttrQuotes {
+    /// Returns the insertion before, the (maybe escaped) value and the insertion after a property
+    /// value, regarding this quote selection.
+    pub fn quote<'v>(
+        &self,
+        cfg: &PropertyConfig,
+        value: &'v str,
+    ) -> (Insertion, Cow<'v, str>, Insertion) {
+        let preferred = match self {
+            AttrQuotes::Config => {
+                let value = match cfg.value_after {
+                    Single(quote @ ('"' | '\'')) if cfg.value_before == Single(quote) => {
+                        escape_quote(value, quote)
+                    }
+                    _ => Cow::Borrowed(value),
+                };
+                return (cfg.value_before, value, cfg.value_after);
+            }
+            AttrQuotes::Prefer(c) => *c,
+        };
+        let other = if preferred == '\'' { '"' } else { '\'' };
+        let quote = if value.contains(preferred) && !value.contains(other) {
+            other
+        } else {
+            preferred
+        };
+        (Single(quote), escape_quote(value, quote), Single(quote))
+    }
+}
+
+/// Internal method, which escapes the quote character `quote` within a property value.
+fn escape_quote(value: &str, quote: char) -> Cow<'_, str> {
+    if value.contains(quote) {
+        let escaped = if quote == '"' { "&quot;" } else { "&#39;" };
+        Cow::Owned(value.replace(quote, escaped))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Selector for the rules, tag names will be validated with when `MarkupSth::validate_names` is
+/// enabled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NameRule {
+    /// Names must not be empty and must not contain whitespaces or any of the delimiting
+    /// characters `<`, `>`, `/`, `=`, `"` and `'`.
+    Delimiters,
+    /// HTML tag names: An ASCII letter followed by ASCII letters, digits or hyphens.
+    Html,
+    /// XML names: A `NameStartChar` followed by `NameChar`s, as defined in the XML specification.
+    Xml,
+}
+
+impl NameRule {
+    /// Checks, whether `name` is a valid name by this rule.
+    pub fn is_valid(&self, name: &str) -> bool {
+        let mut chars = name.chars();
+        let first = match chars.next() {
+            Some(c) => c,
+            None => return false,
+        };
+        match self {
+            NameRule::Delimiters => !name
+                .chars()
+                .any(|c| c.is_whitespace() || matches!(c, '<' | '>' | '/' | '=' | '"' | '\'')),
+            NameRule::Html => {
+                first.is_ascii_alphabetic() && chars.all(|c| c.is_ascii_alphanumeric() || c == '-')
+            }
+            NameRule::Xml => is_xml_name_start_char(first) && chars.all(is_xml_name_char),
+        }
+    }
+}
+
+impl From<&Language> for NameRule {
+    fn from(ml: &Language) -> NameRule {
+        match ml {
+            Language::Html | Language::Html5 | Language::XHtml => NameRule::Html,
+            Language::Xml => NameRule::Xml,
+            Language::Other(_) => NameRule::Delimiters,
+        }
+    }
+}
+
+/// Internal check method for a `NameStartChar` in XML.
+fn is_xml_name_start_char(c: char) -> bool {
+    matches!(c,
+        ':' | 'A'..='Z' | '_' | 'a'..='z'
+        | '\u{C0}'..='\u{D6}' | '\u{D8}'..='\u{F6}' | '\u{F8}'..='\u{2FF}'
+        | '\u{370}'..='\u{37D}' | '\u{37F}'..='\u{1FFF}' | '\u{200C}'..='\u{200D}'
+        | '\u{2070}'..='\u{218F}' | '\u{2C00}'..='\u{2FEF}' | '\u{3001}'..='\u{D7FF}'
+        | '\u{F900}'..='\u{FDCF}' | '\u{FDF0}'..='\u{FFFD}' | '\u{10000}'..='\u{EFFFF}')
+}
+
+/// Internal check method for a `NameChar` in XML.
+fn is_xml_name_char(c: char) -> bool {
+    is_xml_name_start_char(c)
+        || matches!(c,
+            '-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}')
+}
+
 impl From<Language> for SyntaxConfig {
     fn from(cfg_sel: Language) -> SyntaxConfig {
         match cfg_sel {
-            Language::Html => SyntaxConfig {
+            Language::Html | Language::Html5 => SyntaxConfig {
                 doctype: Some(r#"<!DOCTYPE html>"#.to_string()),
                 self_closing: Some(SelfClosingTagConfig {
                     before: Single('<'),
@@ -186,12 +681,18 @@ impl From<Language> for SyntaxConfig {
                     value_before: Single('\"'),
                     value_after: Single('\"'),
                     name_separator: Single('='),
-                    value_separator: Single(' '),
+                    value_separator: AttrSeparator::Inline(Single(' ')),
                 }),
+                text_escapes: markup_text_escapes(),
+                attr_escapes: markup_attr_escapes(),
             },
-            Language::Xml => SyntaxConfig {
+            Language::XHtml => SyntaxConfig {
                 doctype: Some(
-                    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#.to_string(),
+                    concat![
+                        r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "#,
+                        r#""http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd">"#
+                    ]
+                    .to_string(),
                 ),
                 self_closing: Some(SelfClosingTagConfig {
                     before: Single('<'),
@@ -210,14 +711,212 @@ impl From<Language> for SyntaxConfig {
                     value_before: Single('\"'),
                     value_after: Single('\"'),
                     name_separator: Single('='),
-                    value_separator: Single(' '),
+                    value_separator: AttrSeparator::Inline(Single(' ')),
+                }),
+                text_escapes: markup_text_escapes(),
+                attr_escapes: markup_attr_escapes(),
+            },
+            Language::Xml => SyntaxConfig {
+                doctype: Some(XmlDeclaration::default().to_string()),
+                self_closing: Some(SelfClosingTagConfig {
+                    before: Single('<'),
+                    after: Triple(' ', '/', '>'),
+                }),
+                tag_pairs: Some(TagPairConfig {
+                    opening_before: Single('<'),
+                    opening_after: Single('>'),
+                    closing_before: Double('<', '/'),
+                    closing_after: Single('>'),
+                }),
+                properties: Some(PropertyConfig {
+                    initiator: Single(' '),
+                    name_before: Nothing,
+                    name_after: Nothing,
+                    value_before: Single('\"'),
+                    value_after: Single('\"'),
+                    name_separator: Single('='),
+                    value_separator: AttrSeparator::Inline(Single(' ')),
                 }),
+                text_escapes: markup_text_escapes(),
+                attr_escapes: markup_attr_escapes(),
             },
             Language::Other(cfg) => cfg,
         }
     }
 }
 
+impl SyntaxConfig {
+    /// Checks this configuration for obvious mistakes, e.g. a syntax without any tag elements, tags
+    /// without any delimiters, or a tag-based syntax (tags starting with `<`) without properties.
+    pub fn validate(&self) -> Result<()> {
+        if self.self_closing.is_none() && self.tag_pairs.is_none() {
+            return Err(
+                "MarkupSth: invalid syntax, neither self-closing tags nor tag pairs".into(),
+            );
+        }
+        if let Some(sc) = &self.self_closing {
+            if sc.before == Nothing && sc.after == Nothing {
+                return Err(
+                    "MarkupSth: invalid syntax, self-closing tags have no delimiters".into(),
+                );
+            }
+        }
+        if let Some(tp) = &self.tag_pairs {
+            if tp.opening_before == Nothing && tp.opening_after == Nothing {
+                return Err("MarkupSth: invalid syntax, opening tags have no delimiters".into());
+            }
+            if tp.closing_before == Nothing && tp.closing_after == Nothing {
+                return Err("MarkupSth: invalid syntax, closing tags have no delimiters".into());
+            }
+        }
+        let tag_based = self.self_closing.as_ref().map(|sc| sc.before) == Some(Single('<'))
+            || self.tag_pairs.as_ref().map(|tp| tp.opening_before) == Some(Single('<'));
+        if tag_based && self.properties.is_none() {
+            return Err("MarkupSth: invalid syntax, tags starting with '<' need properties".into());
+        }
+        Ok(())
+    }
+}
+
+/// Parses a Markup document, written with syntax `cfg`, back into a sequence of tags and text.
+///
+/// This is not a full parser, it is just good enough to read back documents generated by this
+/// crate, e.g. to assert their structure independent of formatting. Properties of tags will be
+/// skipped. Text content will be trimmed and stored in the `String` of the `TagSequence`, text
+/// consisting only of whitespaces (formatting) will be skipped. A leading doctype results in an
+/// `Initial` sequence. If opening and self-closing tags cannot be distinguished by syntax (as in
+/// HTML), tags without a matching closing tag will be treated as self-closing tags.
+pub fn parse(input: &str, cfg: &SyntaxConfig) -> Result<Vec<TagSequence>> {
+    let mut seqs = Vec::new();
+    let mut rest = input;
+    if let Some(doctype) = cfg.doctype.as_ref() {
+        if let Some(r) = rest.trim_start().strip_prefix(doctype.as_str()) {
+            seqs.push(TagSequence::initial());
+            rest = r;
+        }
+    }
+
+    // Possible tags as (sequence, insertion before, insertions after), longest first.
+    let mut patterns: Vec<(Sequence, String, Vec<String>)> = Vec::new();
+    if let Some(tp) = cfg.tag_pairs.as_ref() {
+        patterns.push((
+            Sequence::Closing,
+            tp.closing_before.to_string(),
+            vec![tp.closing_after.to_string()],
+        ));
+        patterns.push((
+            Sequence::Opening,
+            tp.opening_before.to_string(),
+            vec![tp.opening_after.to_string()],
+        ));
+    }
+    if let Some(sc) = cfg.self_closing.as_ref() {
+        patterns.push((
+            Sequence::SelfClosing,
+            sc.before.to_string(),
+            vec![sc.after.to_string()],
+        ));
+    }
+    if patterns.iter().any(|p| p.1.is_empty() || p.2[0].is_empty()) {
+        return Err("syntax::parse: tags without insertions cannot be parsed".into());
+    }
+    // Merge opening and self-closing tags with same insertion before, they can be ambiguous.
+    if let (Some(o), Some(s)) = (
+        patterns.iter().position(|p| p.0 == Sequence::Opening),
+        patterns.iter().position(|p| p.0 == Sequence::SelfClosing),
+    ) {
+        if patterns[o].1 == patterns[s].1 {
+            let after = patterns.remove(s).2.remove(0);
+            patterns[o].2.push(after);
+        }
+    }
+    patterns.sort_by_key(|p| std::cmp::Reverse(p.1.len()));
+    for p in patterns.iter_mut() {
+        p.2.sort_by_key(|a| std::cmp::Reverse(a.len()));
+    }
+    let sc_after = cfg.self_closing.as_ref().map(|sc| sc.after.to_string());
+    let quote = cfg.properties.as_ref().and_then(|p| match p.value_before {
+        Single(c) => Some(c),
+        _ => None,
+    });
+
+    // Stack of open tags as (index, could also be a self-closing tag).
+    let mut open: Vec<(usize, bool)> = Vec::new();
+    let mut text = String::new();
+    while !rest.is_empty() {
+        let pattern = patterns.iter().find(|p| rest.starts_with(p.1.as_str()));
+        let (seq, before, afters) = match pattern {
+            Some(p) => p,
+            None => {
+                let c = rest.chars().next().unwrap();
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+        };
+        if !text.trim().is_empty() {
+            seqs.push(TagSequence(Sequence::Text, text.trim().to_string()));
+        }
+        text.clear();
+
+        // Find the end of the tag, but skip quoted property values.
+        let body = &rest[before.len()..];
+        let mut in_quote = false;
+        let mut end = None;
+        for (i, c) in body.char_indices() {
+            if Some(c) == quote {
+                in_quote = !in_quote;
+            } else if !in_quote {
+                if let Some(a) = afters.iter().find(|a| body[i..].starts_with(a.as_str())) {
+                    end = Some((i, a));
+                    break;
+                }
+            }
+        }
+        let (end, after) = match end {
+            Some(e) => e,
+            None => return Err(format!("syntax::parse: unterminated tag '{}'", rest).into()),
+        };
+        let name: String = body[..end]
+            .chars()
+            .take_while(|c| !c.is_whitespace())
+            .collect();
+        let mut is_ambiguous = false;
+        let seq = match (seq, sc_after.as_ref()) {
+            (Sequence::Opening, Some(sc)) if afters.len() > 1 && sc == after => {
+                if afters[0] == afters[1] {
+                    is_ambiguous = true;
+                    Sequence::Opening
+                } else {
+                    Sequence::SelfClosing
+                }
+            }
+            _ => seq.clone(),
+        };
+        match seq {
+            Sequence::Opening => open.push((seqs.len(), is_ambiguous)),
+            Sequence::Closing => {
+                // Ambiguous tags after the matching opening tag were self-closing tags.
+                if let Some(o) = open.iter().rposition(|(i, _)| seqs[*i].1 == name) {
+                    for (i, _) in open.drain(o..).skip(1).filter(|(_, a)| *a) {
+                        seqs[i].0 = Sequence::SelfClosing;
+                    }
+                }
+            }
+            _ => {}
+        }
+        seqs.push(TagSequence::from(&seq, &name));
+        rest = &body[end + after.len()..];
+    }
+    if !text.trim().is_empty() {
+        seqs.push(TagSequence(Sequence::Text, text.trim().to_string()));
+    }
+    for (i, _) in open.into_iter().filter(|(_, a)| *a) {
+        seqs[i].0 = Sequence::SelfClosing;
+    }
+    Ok(seqs)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -229,6 +928,240 @@ mod tests {
         let _ = SyntaxConfig::from(Language::Other(cfg));
     }
 
+    #[test]
+    fn html_tag_names() {
+        assert_eq!(HtmlTag::Div.as_str(), "div");
+        assert_eq!(HtmlTag::H1.to_string(), "h1");
+        assert!(HtmlTag::Img.is_void());
+        assert!(HtmlTag::Wbr.is_void());
+        assert!(!HtmlTag::P.is_void());
+        assert!(!HtmlTag::Script.is_void());
+    }
+
+    #[test]
+    fn non_ascii_references() {
+        assert!(matches!(escape_non_ascii("plain"), Cow::Borrowed("plain")));
+        assert_eq!(escape_non_ascii("café"), "caf&#xE9;");
+        assert_eq!(escape_non_ascii("ü ß"), "&#xFC; &#xDF;");
+        assert_eq!(escape_non_ascii("😀!"), "&#x1F600;!");
+        assert_eq!(escape_non_ascii("𝄞"), "&#x1D11E;");
+    }
+
+    #[test]
+    fn typography_substitutions() {
+        assert_eq!(typography("no quotes - here"), "no quotes - here");
+        assert_eq!(
+            typography(r#""Hello," she said, "world""#),
+            "&ldquo;Hello,&rdquo; she said, &ldquo;world&rdquo;"
+        );
+        assert_eq!(typography(r#"("quoted")"#), "(&ldquo;quoted&rdquo;)");
+        assert_eq!(typography("wait--what"), "wait&mdash;what");
+        assert_eq!(typography("a --- b"), "a &mdash;- b");
+    }
+
+    #[test]
+    fn escape_tables() {
+        let escapes = vec![('|', "\\|".to_string()), ('*', "\\*".to_string())];
+        assert_eq!(escape_with("a | b", &escapes), "a \\| b");
+        assert_eq!(escape_with("*bold*", &escapes), "\\*bold\\*");
+        assert!(matches!(escape_with("plain", &escapes), Cow::Borrowed(_)));
+        assert_eq!(
+            escape_with("a < b", &markup_text_escapes()),
+            escape_text("a < b")
+        );
+    }
+
+    #[test]
+    fn xml_declarations() {
+        assert_eq!(
+            XmlDeclaration::default().to_string(),
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#
+        );
+        let decl = XmlDeclaration {
+            version: "1.1".to_string(),
+            encoding: Some("ISO-8859-1".to_string()),
+            standalone: Some(false),
+        };
+        assert_eq!(
+            decl.to_string(),
+            r#"<?xml version="1.1" encoding="ISO-8859-1" standalone="no"?>"#
+        );
+        let decl = XmlDeclaration {
+            encoding: None,
+            standalone: None,
+            ..Default::default()
+        };
+        assert_eq!(decl.to_string(), r#"<?xml version="1.0"?>"#);
+    }
+
+    #[test]
+    fn validate_configs() {
+        for ml in [
+            Language::Html,
+            Language::Html5,
+            Language::XHtml,
+            Language::Xml,
+        ] {
+            assert!(SyntaxConfig::from(ml).validate().is_ok());
+        }
+
+        let mut cfg = SyntaxConfig::from(Language::Html);
+        cfg.properties = None;
+        assert!(cfg.validate().is_err());
+
+        let mut cfg = SyntaxConfig::from(Language::Xml);
+        cfg.self_closing = Some(SelfClosingTagConfig {
+            before: Nothing,
+            after: Nothing,
+        });
+        assert!(cfg.validate().is_err());
+
+        let mut cfg = SyntaxConfig::from(Language::Xml);
+        cfg.self_closing = None;
+        assert!(cfg.validate().is_ok());
+        cfg.tag_pairs = None;
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn name_rules() {
+        for name in ["div", "h1", "my-element"] {
+            assert!(NameRule::Delimiters.is_valid(name));
+            assert!(NameRule::Html.is_valid(name));
+            assert!(NameRule::Xml.is_valid(name));
+        }
+        for name in ["", "div class", "a<b", "a/b"] {
+            assert!(!NameRule::Delimiters.is_valid(name));
+            assert!(!NameRule::Html.is_valid(name));
+            assert!(!NameRule::Xml.is_valid(name));
+        }
+        assert!(NameRule::Xml.is_valid("straße"));
+        assert!(NameRule::Xml.is_valid("xs:element"));
+        assert!(!NameRule::Html.is_valid("straße"));
+        assert!(!NameRule::Html.is_valid("xs:element"));
+        assert!(!NameRule::Xml.is_valid("1st"));
+        assert!(!NameRule::Xml.is_valid("-tag"));
+    }
+
+    fn text(text: &str) -> TagSequence {
+        TagSequence(Sequence::Text, text.to_string())
+    }
+
+    #[test]
+    fn parse_html_fixture() {
+        let cfg = SyntaxConfig::from(Language::Html);
+        let seqs = parse(&crate::testfile("formatted_html_auto_indent.html"), &cfg).unwrap();
+        assert_eq!(
+            seqs,
+            vec![
+                TagSequence::initial(),
+                TagSequence::opening("html"),
+                TagSequence::opening("head"),
+                TagSequence::opening("title"),
+                text("New Website"),
+                TagSequence::closing("title"),
+                TagSequence::self_closing("link"),
+                TagSequence::closing("head"),
+                TagSequence::opening("body"),
+                TagSequence::opening("section"),
+                TagSequence::opening("div"),
+                TagSequence::opening("div"),
+                TagSequence::self_closing("img"),
+                TagSequence::closing("div"),
+                TagSequence::opening("p"),
+                text("This is HTML"),
+                TagSequence::closing("p"),
+                TagSequence::closing("div"),
+                TagSequence::closing("section"),
+                TagSequence::closing("body"),
+                TagSequence::closing("html"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_xml_fixture() {
+        let cfg = SyntaxConfig::from(Language::Xml);
+        let seqs = parse(&crate::testfile("formatted_xml_auto_indent.xml"), &cfg).unwrap();
+        let mut expected = vec![
+            TagSequence::initial(),
+            TagSequence::opening("directory"),
+            TagSequence::opening("title"),
+            text("Wikipedia List of Cities"),
+            TagSequence::closing("title"),
+        ];
+        for name in ["Hamburg", "Munich"] {
+            expected.extend([
+                TagSequence::opening("entry"),
+                TagSequence::opening("keyword"),
+                text(name),
+                TagSequence::closing("keyword"),
+                TagSequence::opening("entrystext"),
+                text(&format!("{} is the residence of ...", name)),
+                TagSequence::closing("entrystext"),
+                TagSequence::closing("entry"),
+            ]);
+        }
+        expected.push(TagSequence::closing("directory"));
+        assert_eq!(seqs, expected);
+    }
+
+    #[test]
+    fn parse_self_closing_xml_with_properties() {
+        let cfg = SyntaxConfig::from(Language::Xml);
+        let seqs = parse(r#"<a><b x="1 />" y="2" /><c z="3">Text</c></a>"#, &cfg).unwrap();
+        assert_eq!(
+            seqs,
+            vec![
+                TagSequence::opening("a"),
+                TagSequence::self_closing("b"),
+                TagSequence::opening("c"),
+                text("Text"),
+                TagSequence::closing("c"),
+                TagSequence::closing("a"),
+            ]
+        );
+        assert!(parse("<a><b", &cfg).is_err());
+    }
+
+    #[test]
+    fn escape_text_content() {
+        assert!(matches!(escape_text("nothing"), Cow::Borrowed("nothing")));
+        assert_eq!(
+            escape_text("a < b && c > d"),
+            "a &lt; b &amp;&amp; c &gt; d"
+        );
+    }
+
+    #[test]
+    fn attr_quotes() {
+        let cfg = SyntaxConfig::from(Language::Html).properties.unwrap();
+        let quote = |q: AttrQuotes, value: &str| {
+            let (before, value, after) = q.quote(&cfg, value);
+            format!("{}{}{}", before, value, after)
+        };
+
+        let prefer = AttrQuotes::Prefer('"');
+        assert_eq!(quote(prefer, "neither"), r#""neither""#);
+        assert_eq!(quote(prefer, r#"say "hi""#), r#"'say "hi"'"#);
+        assert_eq!(quote(prefer, "it's"), r#""it's""#);
+        assert_eq!(
+            quote(prefer, r#"it's "both""#),
+            r#""it's &quot;both&quot;""#
+        );
+
+        let prefer = AttrQuotes::Prefer('\'');
+        assert_eq!(quote(prefer, "neither"), "'neither'");
+        assert_eq!(quote(prefer, "it's"), r#""it's""#);
+        assert_eq!(quote(prefer, r#"it's "both""#), r#"'it&#39;s "both"'"#);
+
+        assert_eq!(
+            quote(AttrQuotes::Config, r#"say "hi""#),
+            r#""say &quot;hi&quot;""#
+        );
+        assert_eq!(quote(AttrQuotes::Config, "it's"), r#""it's""#);
+    }
+
     #[test]
     fn insertion_to_string() {
         assert_eq!(Nothing.to_string(), "".to_string());
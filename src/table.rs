@@ -0,0 +1,90 @@
+//! This module contains helpers of `MarkupSth` for generating HTML tables from rows of data.
+
+use crate::{MarkupSth, Result};
+
+impl MarkupSth<'_> {
+    /// Inserts a complete table with a header row of `headers` and one row per entry of `rows`.
+    /// All cells will be escaped like `text()`. Every row must have as many columns as there are
+    /// headers, otherwise an error is returned and nothing will be inserted.
+    pub fn table(&mut self, headers: &[&str], rows: &[Vec<String>]) -> Result<()> {
+        if let Some((i, row)) = rows
+            .iter()
+            .enumerate()
+            .find(|(_, row)| row.len() != headers.len())
+        {
+            return Err(format!(
+                "MarkupSth: table row {} has {} columns, but there are {} headers",
+                i,
+                row.len(),
+                headers.len()
+            )
+            .into());
+        }
+
+        self.open("table")?;
+        self.open("thead")?;
+        self.open("tr")?;
+        for header in headers.iter() {
+            self.open("th")?;
+            self.text(header)?;
+            self.close()?;
+        }
+        self.close()?;
+        self.close()?;
+        self.open("tbody")?;
+        for row in rows.iter() {
+            self.open("tr")?;
+            for cell in row.iter() {
+                self.open("td")?;
+                self.text(cell)?;
+                self.close()?;
+            }
+            self.close()?;
+        }
+        self.close()?;
+        self.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testfile, Formatter, HtmlBlockIndent, Language, MarkupSth};
+
+    fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn table_from_rows() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        mus.set_formatter(Box::new(HtmlBlockIndent::new()));
+        mus.table(
+            &["City", "Country", "Population"],
+            &rows(&[
+                &["Hamburg", "Germany", "1.9 M"],
+                &["Munich", "Germany", "< 1.6 M"],
+            ]),
+        )
+        .unwrap();
+        mus.finalize().unwrap();
+
+        assert_eq!(document, testfile("formatted_html_table.html"));
+    }
+
+    #[test]
+    fn table_with_ragged_row() {
+        let mut document = String::new();
+        let mut mus = MarkupSth::new(&mut document, Language::Html).unwrap();
+        let err = mus
+            .table(&["a", "b"], &rows(&[&["1", "2"], &["3"]]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "MarkupSth: table row 1 has 1 columns, but there are 2 headers"
+        );
+        assert_eq!(mus.buffer(), Some(""));
+    }
+}